@@ -0,0 +1,114 @@
+extern crate criterion;
+extern crate quisquis;
+
+use criterion::{Criterion, criterion_group, criterion_main, black_box};
+use rand::{thread_rng, Rng};
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey};
+
+use quisquis::misc::{QPublicKey, GENERATOR_G, GENERATOR_H, MAX_AMOUNT_PER_OUTPUT};
+use quisquis::misc::{amount_to_key, single_base_product, double_base_product, ratio};
+use quisquis::misc::{hash_simple_tx, hash_special_tx};
+use quisquis::nummatus_nizk::NummatusPoK;
+use quisquis::simple_nizk::SimplePoK;
+
+// An owned Quisquis account (pubkey, commitment), its Pedersen commitment and secret key
+fn owned_account(secp_inst: &Secp256k1) -> (QPublicKey, QPublicKey, PublicKey, SecretKey) {
+    let mut rng = thread_rng();
+    let g = PublicKey::from_slice(secp_inst, &GENERATOR_G).unwrap();
+    let h = PublicKey::from_slice(secp_inst, &GENERATOR_H).unwrap();
+    let key = SecretKey::new(secp_inst, &mut rng);
+    let r1 = SecretKey::new(secp_inst, &mut rng);
+    let r2 = SecretKey::new(secp_inst, &mut rng);
+    let amount = amount_to_key(secp_inst, rng.gen_range(1, MAX_AMOUNT_PER_OUTPUT));
+
+    let x = single_base_product(secp_inst, g, r1);
+    let y = single_base_product(secp_inst, x, key.clone());
+    let pubkey = QPublicKey { x, y };
+    let commitment = QPublicKey {
+        x: single_base_product(secp_inst, x, r2.clone()),
+        y: double_base_product(secp_inst, g, y, amount.clone(), r2),
+    };
+    let pedersen_com = double_base_product(secp_inst, g, h, amount, key.clone());
+
+    (pubkey, commitment, pedersen_com, key)
+}
+
+fn random_point(secp_inst: &Secp256k1) -> PublicKey {
+    PublicKey::from_secret_key(secp_inst, &SecretKey::new(secp_inst, &mut thread_rng())).unwrap()
+}
+
+fn bench_misc(c: &mut Criterion) {
+    c.bench_function("single_base_product", |b| {
+        let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+        let p1 = random_point(&secp_inst);
+        let k1 = SecretKey::new(&secp_inst, &mut thread_rng());
+        b.iter(|| single_base_product(&secp_inst, black_box(p1), black_box(k1.clone())))
+    });
+    c.bench_function("double_base_product", |b| {
+        let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+        let (p1, p2) = (random_point(&secp_inst), random_point(&secp_inst));
+        let k1 = SecretKey::new(&secp_inst, &mut thread_rng());
+        let k2 = SecretKey::new(&secp_inst, &mut thread_rng());
+        b.iter(|| double_base_product(&secp_inst, black_box(p1), black_box(p2), black_box(k1.clone()), black_box(k2.clone())))
+    });
+    c.bench_function("ratio", |b| {
+        let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+        let (p1, p2) = (random_point(&secp_inst), random_point(&secp_inst));
+        b.iter(|| ratio(&secp_inst, black_box(p1), black_box(p2)))
+    });
+    c.bench_function("hash_simple_tx", |b| {
+        let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+        let (p1, p2) = (random_point(&secp_inst), random_point(&secp_inst));
+        b.iter(|| hash_simple_tx(&secp_inst, p1, p2, p1, p2, p1, p2, p1, black_box(p2), None))
+    });
+    c.bench_function("hash_special_tx", |b| {
+        let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+        let (p1, p2) = (random_point(&secp_inst), random_point(&secp_inst));
+        b.iter(|| hash_special_tx(&secp_inst, p1, p2, p1, p2, p1, p2, p1, p2, black_box(p1), None))
+    });
+    c.bench_function("amount_to_key", |b| {
+        let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+        b.iter(|| amount_to_key(&secp_inst, black_box(MAX_AMOUNT_PER_OUTPUT)))
+    });
+}
+
+fn bench_simple_pok(c: &mut Criterion) {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let h = PublicKey::from_slice(&secp_inst, &GENERATOR_H).unwrap();
+    let (pubkey, commitment, pedersen_com, key) = owned_account(&secp_inst);
+    let pok = SimplePoK::create_pok_from_representation(pubkey, commitment, pedersen_com, key.clone(), h, None);
+
+    c.bench_function("SimplePoK create", move |b| {
+        b.iter(|| SimplePoK::create_pok_from_representation(pubkey, commitment, pedersen_com, key.clone(), h, None))
+    });
+    c.bench_function("SimplePoK verify", move |b| {
+        b.iter(|| assert!(SimplePoK::verify_pok(pubkey, commitment, pedersen_com, h, pok.clone(), None)))
+    });
+}
+
+fn bench_nummatus_pok(c: &mut Criterion) {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let h = PublicKey::from_slice(&secp_inst, &GENERATOR_H).unwrap();
+    let (pubkey, commitment, pedersen_com, key) = owned_account(&secp_inst);
+    let pok = NummatusPoK::create_pok_from_representation(pubkey, commitment, pedersen_com, key.clone(), h, None);
+
+    let decoy_pubkey = QPublicKey { x: random_point(&secp_inst), y: random_point(&secp_inst) };
+    let decoy_commitment = QPublicKey { x: random_point(&secp_inst), y: random_point(&secp_inst) };
+    let decoy_key = SecretKey::new(&secp_inst, &mut thread_rng());
+    let decoy_pedersen_com = single_base_product(&secp_inst, h, decoy_key.clone());
+
+    c.bench_function("NummatusPoK create owned", move |b| {
+        b.iter(|| NummatusPoK::create_pok_from_representation(pubkey, commitment, pedersen_com, key.clone(), h, None))
+    });
+    c.bench_function("NummatusPoK create decoy", move |b| {
+        b.iter(|| NummatusPoK::create_pok_from_decoy(decoy_pubkey, decoy_commitment, decoy_pedersen_com, decoy_key.clone(), h, None))
+    });
+    c.bench_function("NummatusPoK verify", move |b| {
+        b.iter(|| assert!(NummatusPoK::verify_pok(pubkey, commitment, pedersen_com, h, pok.clone(), None)))
+    });
+}
+
+criterion_group!(benches, bench_misc, bench_simple_pok, bench_nummatus_pok);
+criterion_main!(benches);
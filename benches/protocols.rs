@@ -0,0 +1,41 @@
+extern crate criterion;
+extern crate quisquis;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+
+use quisquis::nummatus_exchange::NummatusExchange;
+use quisquis::simple_exchange::SimpleExchange;
+
+// Anonymity-set sizes for the full protocol runs; the owned list is a quarter of each
+const ANON_LIST_SIZES: [usize; 3] = [10, 100, 1000];
+
+fn bench_simple(c: &mut Criterion) {
+    c.bench_function_over_inputs("Simple generate_proof", |b, &&size| {
+        let mut exch = SimpleExchange::new(size / 4);
+        b.iter(|| exch.generate_proof())
+    }, ANON_LIST_SIZES.iter());
+
+    c.bench_function_over_inputs("Simple verify", |b, &&size| {
+        let proof = SimpleExchange::new(size / 4).generate_proof();
+        b.iter(|| assert!(proof.verify()))
+    }, ANON_LIST_SIZES.iter());
+}
+
+fn bench_nummatus(c: &mut Criterion) {
+    c.bench_function_over_inputs("Nummatus generate_proof", |b, &&size| {
+        let mut exch = NummatusExchange::new(size, size / 4);
+        b.iter(|| exch.generate_proof())
+    }, ANON_LIST_SIZES.iter());
+
+    c.bench_function_over_inputs("Nummatus verify", |b, &&size| {
+        let proof = NummatusExchange::new(size, size / 4).generate_proof();
+        b.iter(|| assert!(proof.verify()))
+    }, ANON_LIST_SIZES.iter());
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_simple, bench_nummatus
+}
+criterion_main!(benches);
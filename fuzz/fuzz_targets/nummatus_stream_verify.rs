@@ -0,0 +1,13 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use quisquis::nummatus_stream::NummatusStreamVerifier;
+
+// The streaming verifier reads the same format record by record; it must
+// also return an error or a verdict on any input
+fuzz_target!(|data: &[u8]| {
+    let mut reader = &data[..];
+    if let Ok(mut verifier) = NummatusStreamVerifier::from_header(&mut reader) {
+        let _ = verifier.verify_all(&mut reader);
+    }
+});
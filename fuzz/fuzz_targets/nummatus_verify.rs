@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use quisquis::nummatus_exchange::Nummatus;
+
+// Decoding arbitrary bytes either fails cleanly or gives a proof that
+// verifies to true or false, never a panic
+fuzz_target!(|data: &[u8]| {
+    if let Ok(proof) = Nummatus::read_from(&mut &data[..]) {
+        proof.verify();
+    }
+});
@@ -1,12 +1,15 @@
 cargo build --release
-cargo run --release --bin nummatus 100 25 -n 100
-cargo run --release --bin nummatus 100 50 -n 100
-cargo run --release --bin nummatus 100 75 -n 100
+cargo run --release --bin nummatus run 100 25 -n 100
+cargo run --release --bin nummatus run 100 50 -n 100
+cargo run --release --bin nummatus run 100 75 -n 100
 
-cargo run --release --bin nummatus 1000 250 -n 100
-cargo run --release --bin nummatus 1000 500 -n 100
-cargo run --release --bin nummatus 1000 750 -n 100
+cargo run --release --bin nummatus run 1000 250 -n 100
+cargo run --release --bin nummatus run 1000 500 -n 100
+cargo run --release --bin nummatus run 1000 750 -n 100
 
-cargo run --release --bin nummatus 10000 2500 -n 100
-cargo run --release --bin nummatus 10000 5000 -n 100
-cargo run --release --bin nummatus 10000 7500 -n 100
+cargo run --release --bin nummatus run 10000 2500 -n 100
+cargo run --release --bin nummatus run 10000 5000 -n 100
+cargo run --release --bin nummatus run 10000 7500 -n 100
+
+# Owned-list sizes 1, 10 and 100 at the same anonymity-set sizes, written as CSV for plotting
+# cargo run --release --bin nummatus sweep -a 100,1000,10000 -o 1,10,100 -n 100 --output nummatus-sweep.csv
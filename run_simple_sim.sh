@@ -1,12 +1,15 @@
 cargo build --release
-cargo run --release --bin simple 25 -n 100
-cargo run --release --bin simple 50 -n 100
-cargo run --release --bin simple 75 -n 100
+cargo run --release --bin simple run 25 -n 100
+cargo run --release --bin simple run 50 -n 100
+cargo run --release --bin simple run 75 -n 100
 
-cargo run --release --bin simple 250 -n 100
-cargo run --release --bin simple 500 -n 100
-cargo run --release --bin simple 750 -n 100
+cargo run --release --bin simple run 250 -n 100
+cargo run --release --bin simple run 500 -n 100
+cargo run --release --bin simple run 750 -n 100
 
-cargo run --release --bin simple 2500 -n 100
-cargo run --release --bin simple 5000 -n 100
-cargo run --release --bin simple 7500 -n 100
+cargo run --release --bin simple run 2500 -n 100
+cargo run --release --bin simple run 5000 -n 100
+cargo run --release --bin simple run 7500 -n 100
+
+# Same configurations as above, written as CSV for plotting
+# cargo run --release --bin simple sweep -o 25,50,75,250,500,750,2500,5000,7500 -n 100 --output simple-sweep.csv
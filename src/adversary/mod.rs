@@ -0,0 +1,217 @@
+use std::mem;
+
+use rand::Rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::SecretKey;
+
+use crate::misc::MAX_AMOUNT_PER_OUTPUT;
+use crate::misc::pedersen_commitment;
+use crate::ledger::{Ledger, LedgerSnapshot};
+use crate::dlog::DiscreteLogTable;
+use crate::nummatus_nizk::NummatusPoK;
+use crate::simple_nizk::SimplePoK;
+use crate::nummatus_exchange::{Nummatus, NummatusExchange};
+use crate::simple_exchange::{Simple, SimpleExchange};
+
+// Owner of the dishonest exchange in the simulated ledger; every other account belongs to VICTIM
+const ATTACKER: usize = 0;
+const VICTIM: usize = 1;
+
+// Ways a dishonest exchange may try to overstate its reserves or pass off a
+// malformed proof. Each one is caught by the verifier.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attack {
+  // Claims an account of someone else, guessing the secret key it cannot know
+  ForeignAccount,
+  // Commits to more than the balance of an account it does own
+  InflatedAmount,
+  // Presents a proof made at an earlier height after some of its accounts were spent
+  StaleHeight,
+  // Swaps e1 and e2 of a valid NummatusPoK, which keeps their sum
+  SwappedChallenges,
+  // Sets the Pedersen commitment p to d, so that p * d^-1 is the identity
+  IdentityPoints,
+  // Drops the last PoK so that the lists no longer line up
+  MismatchedLengths,
+  // Leaves the Pedersen commitment and PoK of an entry as the zeroed ::new() placeholders
+  PlaceholderEntry,
+}
+
+impl Attack {
+  pub const ALL: [Attack; 7] = [
+    Attack::ForeignAccount,
+    Attack::InflatedAmount,
+    Attack::StaleHeight,
+    Attack::SwappedChallenges,
+    Attack::IdentityPoints,
+    Attack::MismatchedLengths,
+    Attack::PlaceholderEntry,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Attack::ForeignAccount => "foreign-account",
+      Attack::InflatedAmount => "inflated-amount",
+      Attack::StaleHeight => "stale-height",
+      Attack::SwappedChallenges => "swapped-challenges",
+      Attack::IdentityPoints => "identity-points",
+      Attack::MismatchedLengths => "mismatched-lengths",
+      Attack::PlaceholderEntry => "placeholder-entry",
+    }
+  }
+}
+
+// A forged Nummatus proof and the ledger snapshot it is verified against
+pub struct NummatusForgery {
+  pub attack: Attack,
+  pub proof: Nummatus,
+  pub snapshot: LedgerSnapshot,
+}
+
+impl NummatusForgery {
+  pub fn is_rejected(&self) -> bool {
+    self.proof.verify_against_ledger(&self.snapshot, None).is_err()
+  }
+}
+
+// A forged Simplus proof
+pub struct SimpleForgery {
+  pub attack: Attack,
+  pub proof: Simple,
+}
+
+impl SimpleForgery {
+  pub fn is_rejected(&self) -> bool {
+    !self.proof.verify()
+  }
+}
+
+//a ledger with num_accounts accounts at height 1, alternately owned by the attacker and the victim
+fn setup_ledger<R: Rng>(rng: &mut R, num_accounts: usize) -> Ledger {
+  assert!(num_accounts >= 2);
+
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let mut ledger = Ledger::new();
+  ledger.advance();
+  for i in 0..num_accounts {
+    let owner = if i % 2 == 0 { ATTACKER } else { VICTIM };
+    let key = SecretKey::new(&secp_inst, rng);
+    let balance = rng.gen_range(1, MAX_AMOUNT_PER_OUTPUT);
+    ledger.create_account(rng, owner, key, balance);
+  }
+  ledger
+}
+
+//first ledger account of owner at the ledger's height
+fn first_account(ledger: &Ledger, owner: usize) -> usize {
+  ledger.owned_accounts(owner, ledger.height())[0]
+}
+
+// Builds a Nummatus proof over the whole ledger of num_accounts accounts,
+// honest except for attack. With the whole ledger as anonymity set, entry i
+// of the proof is the account with ledger id i.
+pub fn forge_nummatus<R: Rng>(rng: &mut R, attack: Attack, num_accounts: usize) -> NummatusForgery {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT);
+  let mut ledger = setup_ledger(rng, num_accounts);
+  let height = ledger.height();
+
+  let mut exchange = NummatusExchange::from_ledger(&ledger, ATTACKER, height, &table)
+    .expect("balances are below MAX_AMOUNT_PER_OUTPUT");
+  let mut proof = exchange.generate_proof();
+  let h_basepoint = proof.h_basepoint();
+
+  match attack {
+    Attack::ForeignAccount => {
+      let id = first_account(&ledger, VICTIM);
+      let guess = SecretKey::new(&secp_inst, rng);
+      proof.pedersen_com_list[id] = pedersen_commitment(&secp_inst, ledger.g_basepoint(), h_basepoint,
+                                      ledger.balance(id), guess.clone());
+      proof.pok_list[id] = NummatusPoK::create_pok_from_representation(
+                             proof.pubkey_list[id], proof.commitment_list[id], proof.pedersen_com_list[id], guess, h_basepoint, None);
+    },
+    Attack::InflatedAmount => {
+      let id = first_account(&ledger, ATTACKER);
+      proof.pedersen_com_list[id] = pedersen_commitment(&secp_inst, ledger.g_basepoint(), h_basepoint,
+                                      ledger.balance(id) + MAX_AMOUNT_PER_OUTPUT, ledger.key(id));
+      proof.pok_list[id] = NummatusPoK::create_pok_from_representation(
+                             proof.pubkey_list[id], proof.commitment_list[id], proof.pedersen_com_list[id], ledger.key(id), h_basepoint, None);
+    },
+    Attack::StaleHeight => {
+      ledger.advance();
+      ledger.transfer(first_account(&ledger, ATTACKER), first_account(&ledger, VICTIM), 1);
+    },
+    Attack::SwappedChallenges => {
+      let pok = &mut proof.pok_list[first_account(&ledger, ATTACKER)];
+      mem::swap(&mut pok.e1, &mut pok.e2);
+    },
+    Attack::IdentityPoints => {
+      let id = first_account(&ledger, VICTIM);
+      proof.pedersen_com_list[id] = proof.commitment_list[id].y;
+    },
+    Attack::MismatchedLengths => {
+      proof.pok_list.pop();
+    },
+    Attack::PlaceholderEntry => {
+      let placeholder = Nummatus::new(1);
+      let id = first_account(&ledger, VICTIM);
+      proof.pedersen_com_list[id] = placeholder.pedersen_com_list[0];
+      proof.pok_list[id] = placeholder.pok_list[0].clone();
+    },
+  }
+
+  NummatusForgery {
+    attack,
+    proof,
+    snapshot: ledger.snapshot(ledger.height()),
+  }
+}
+
+// As forge_nummatus for Simplus, whose proof lists only the owned accounts in
+// ledger order. None for attacks that do not apply: a Simple proof has a
+// single challenge and is not checked against a ledger height.
+pub fn forge_simple<R: Rng>(rng: &mut R, attack: Attack, num_accounts: usize) -> Option<SimpleForgery> {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT);
+  let ledger = setup_ledger(rng, num_accounts);
+
+  let mut exchange = SimpleExchange::from_ledger(&ledger, ATTACKER, ledger.height(), &table)
+    .expect("balances are below MAX_AMOUNT_PER_OUTPUT");
+  let mut proof = exchange.generate_proof();
+  let h_basepoint = proof.h_basepoint();
+
+  match attack {
+    Attack::ForeignAccount => {
+      let foreign = ledger.account(first_account(&ledger, VICTIM));
+      let guess = SecretKey::new(&secp_inst, rng);
+      proof.pubkey_list[0] = foreign.pubkey;
+      proof.commitment_list[0] = foreign.commitment;
+      proof.pederson_list[0] = pedersen_commitment(&secp_inst, ledger.g_basepoint(), h_basepoint,
+                                 ledger.balance(foreign.id), guess.clone());
+      proof.pok_list[0] = SimplePoK::create_pok_from_representation(
+                            proof.pubkey_list[0], proof.commitment_list[0], proof.pederson_list[0], guess, h_basepoint, None);
+    },
+    Attack::InflatedAmount => {
+      let id = first_account(&ledger, ATTACKER);
+      proof.pederson_list[0] = pedersen_commitment(&secp_inst, ledger.g_basepoint(), h_basepoint,
+                                 ledger.balance(id) + MAX_AMOUNT_PER_OUTPUT, ledger.key(id));
+      proof.pok_list[0] = SimplePoK::create_pok_from_representation(
+                            proof.pubkey_list[0], proof.commitment_list[0], proof.pederson_list[0], ledger.key(id), h_basepoint, None);
+    },
+    Attack::StaleHeight | Attack::SwappedChallenges => return None,
+    Attack::IdentityPoints => {
+      proof.pederson_list[0] = proof.commitment_list[0].y;
+    },
+    Attack::MismatchedLengths => {
+      proof.pok_list.pop();
+    },
+    Attack::PlaceholderEntry => {
+      let placeholder = Simple::new(1);
+      proof.pederson_list[0] = placeholder.pederson_list[0];
+      proof.pok_list[0] = placeholder.pok_list[0].clone();
+    },
+  }
+
+  Some(SimpleForgery { attack, proof })
+}
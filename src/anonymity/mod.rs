@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+
+use rand::RngCore;
+use rand::seq::SliceRandom;
+
+use crate::ledger::Ledger;
+
+// Chooses which ledger accounts go into an exchange's anonymity set at a
+// given height. Every owned account must be included; the rest are decoys.
+// Proof size and proving time are linear in the size of the returned set, so
+// smaller sets are cheaper but give the verifier more to go on.
+pub trait AnonymitySetSelector {
+  // Ledger ids of the anonymity set in ascending order, a superset of owned
+  fn select(&self, ledger: &Ledger, height: u64, owned: &[usize], rng: &mut dyn RngCore) -> Vec<usize>;
+
+  // Short name used when reporting results
+  fn name(&self) -> &'static str;
+}
+
+//live accounts at height that are not in owned, in ledger order
+fn candidates(ledger: &Ledger, height: u64, owned: &[usize]) -> Vec<usize> {
+  let owned: HashSet<usize> = owned.iter().cloned().collect();
+  (0..ledger.num_accounts())
+    .filter(|&id| ledger.is_live(id, height) && !owned.contains(&id))
+    .collect()
+}
+
+//owned plus decoys, sorted so that position in the set reveals nothing
+fn with_owned(owned: &[usize], mut decoys: Vec<usize>) -> Vec<usize> {
+  decoys.extend_from_slice(owned);
+  decoys.sort();
+  decoys.dedup();
+  decoys
+}
+
+// Takes up to count decoys from candidates, closest first by distance(id).
+// Ties are broken at random so equally close accounts are equally likely.
+fn nearest<F: Fn(usize) -> u64>(candidates: &[usize], chosen: &HashSet<usize>, count: usize, rng: &mut dyn RngCore, distance: F) -> Vec<usize> {
+  let mut pool: Vec<usize> = candidates.iter().cloned().filter(|id| !chosen.contains(id)).collect();
+  pool.shuffle(rng);
+  pool.sort_by_key(|&id| distance(id));
+  pool.truncate(count);
+  pool
+}
+
+// The whole ledger at the audited height. Nothing is given up: the owned
+// accounts could be any of the live accounts. Cost grows with the ledger.
+pub struct WholeLedger;
+
+impl AnonymitySetSelector for WholeLedger {
+  fn select(&self, ledger: &Ledger, height: u64, owned: &[usize], _rng: &mut dyn RngCore) -> Vec<usize> {
+    with_owned(owned, candidates(ledger, height, owned))
+  }
+
+  fn name(&self) -> &'static str {
+    "whole-ledger"
+  }
+}
+
+// The owned accounts plus decoys drawn uniformly from the rest of the ledger
+// until the set has size accounts. An observer learns that the owned
+// accounts are among these size accounts rather than the whole ledger, and
+// repeated audits with fresh samples can be intersected to narrow this down.
+pub struct UniformSampling {
+  pub size: usize,
+}
+
+impl AnonymitySetSelector for UniformSampling {
+  fn select(&self, ledger: &Ledger, height: u64, owned: &[usize], rng: &mut dyn RngCore) -> Vec<usize> {
+    let mut pool = candidates(ledger, height, owned);
+    pool.shuffle(rng);
+    pool.truncate(self.size.saturating_sub(owned.len()));
+    with_owned(owned, pool)
+  }
+
+  fn name(&self) -> &'static str {
+    "uniform"
+  }
+}
+
+// For each owned account, decoys_per_output decoys created at the closest
+// block heights. Uniform sampling skews towards old accounts, so owned
+// accounts that are much newer stand out; matching ages removes that signal
+// but clusters the set around the exchange's activity periods.
+pub struct AgeMatched {
+  pub decoys_per_output: usize,
+}
+
+impl AnonymitySetSelector for AgeMatched {
+  fn select(&self, ledger: &Ledger, height: u64, owned: &[usize], rng: &mut dyn RngCore) -> Vec<usize> {
+    let pool = candidates(ledger, height, owned);
+    let mut decoys = HashSet::new();
+
+    for &id in owned {
+      let created = ledger.account(id).created;
+      let picked = nearest(&pool, &decoys, self.decoys_per_output, rng,
+        |decoy| (ledger.account(decoy).created as i64 - created as i64).unsigned_abs());
+      decoys.extend(picked);
+    }
+    with_owned(owned, decoys.into_iter().collect())
+  }
+
+  fn name(&self) -> &'static str {
+    "age-matched"
+  }
+}
+
+// For each owned account, decoys_per_output decoys whose balance falls in the
+// same bucket of width bucket_width, falling back to the nearest buckets.
+// Balances are hidden on a real Quisquis ledger, so this uses the simulator's
+// ground truth to model an exchange with side information on balances. It
+// reveals the balance profile of the owned accounts to the same extent.
+pub struct BalanceBucketMatched {
+  pub decoys_per_output: usize,
+  pub bucket_width: u64,
+}
+
+impl AnonymitySetSelector for BalanceBucketMatched {
+  fn select(&self, ledger: &Ledger, height: u64, owned: &[usize], rng: &mut dyn RngCore) -> Vec<usize> {
+    assert!(self.bucket_width != 0);
+
+    let pool = candidates(ledger, height, owned);
+    let bucket = |id: usize| ledger.balance(id) / self.bucket_width;
+    let mut decoys = HashSet::new();
+
+    for &id in owned {
+      let owned_bucket = bucket(id);
+      let picked = nearest(&pool, &decoys, self.decoys_per_output, rng,
+        |decoy| (bucket(decoy) as i64 - owned_bucket as i64).unsigned_abs());
+      decoys.extend(picked);
+    }
+    with_owned(owned, decoys.into_iter().collect())
+  }
+
+  fn name(&self) -> &'static str {
+    "balance-bucket"
+  }
+}
+
+// Fraction of the live ledger that the anonymity set covers, a crude measure
+// of how much a selector narrows down where the owned accounts can be
+pub fn ledger_coverage(ledger: &Ledger, height: u64, anonymity_set: &[usize]) -> f64 {
+  let live = (0..ledger.num_accounts()).filter(|&id| ledger.is_live(id, height)).count();
+  if live == 0 {
+    return 0.0;
+  }
+  anonymity_set.len() as f64 / live as f64
+}
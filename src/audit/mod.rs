@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey};
+
+use crate::misc::GENERATOR_G;
+use crate::misc::Nonce;
+use crate::misc::pedersen_commitment;
+use crate::ledger::LedgerSnapshot;
+use crate::nummatus_exchange::{Nummatus, LedgerVerifyError};
+
+// What an exchange publishes for an audit: its Nummatus proof and the
+// opening of the product of its Pedersen commitments
+pub struct ReserveClaim {
+  pub exchange: String,
+  pub proof: Nummatus,
+  pub total: u64,                             //claimed reserves
+  pub blinding: SecretKey,                    //sum of the blinding factors of every Pedersen commitment
+}
+
+// Why a single exchange's claim was rejected
+#[derive(Debug)]
+pub enum AuditError {
+  // The proof is not over live ledger accounts or does not verify
+  Ledger(LedgerVerifyError),
+  // (total, blinding) does not open the product of the Pedersen commitments
+  BadOpening,
+}
+
+impl fmt::Display for AuditError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      AuditError::Ledger(e) => write!(f, "{}", e),
+      AuditError::BadOpening => write!(f, "claimed total does not open the reserve commitment"),
+    }
+  }
+}
+
+impl Error for AuditError {}
+
+// A ledger account that two or more exchanges claim to own. With the same h
+// for every exchange at a height, an owned account always gets the Pedersen
+// commitment g^v * h^k, so two claims of it publish the same point, while a
+// decoy's h^beta is fresh for every proof.
+#[derive(Debug)]
+pub struct DoubleClaim {
+  pub account: usize,                         //position of the account in the snapshot
+  pub exchanges: Vec<usize>,                  //positions of the claims that include it with the same commitment
+}
+
+pub struct AuditReport {
+  pub height: u64,
+  pub total_supply: u64,
+  pub results: Vec<Result<u64, AuditError>>,  //verified reserves of each claim, in claim order
+  pub double_claims: Vec<DoubleClaim>,
+}
+
+impl AuditReport {
+  // Sum of the reserves of the claims that verified
+  pub fn total_reserves(&self) -> u64 {
+    self.results.iter().filter_map(|result| result.as_ref().ok()).sum()
+  }
+
+  pub fn passed(&self) -> bool {
+    self.results.iter().all(|result| result.is_ok())
+      && self.double_claims.is_empty()
+      && self.total_reserves() <= self.total_supply
+  }
+}
+
+//checks a single claim against the snapshot, returning its reserves
+fn audit_claim(secp_inst: &Secp256k1, snapshot: &LedgerSnapshot, nonce: Option<&Nonce>, claim: &ReserveClaim) -> Result<u64, AuditError> {
+  claim.proof.verify_against_ledger(snapshot, nonce).map_err(AuditError::Ledger)?;
+
+  let g_basepoint = PublicKey::from_slice(secp_inst, &GENERATOR_G).unwrap();
+  let expected = pedersen_commitment(secp_inst, g_basepoint, claim.proof.h_basepoint(), claim.total, claim.blinding.clone());
+
+  if claim.proof.reserve_commitment() != Some(expected) {
+    return Err(AuditError::BadOpening);
+  }
+  Ok(claim.total)
+}
+
+// Verifies every claim against the snapshot and the auditor's nonce, then
+// checks that no account is claimed by two exchanges and that the verified
+// reserves add up to at most the ledger's total supply at the snapshot height
+pub fn audit(snapshot: &LedgerSnapshot, total_supply: u64, nonce: Option<&Nonce>, claims: &[ReserveClaim]) -> AuditReport {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+  let results: Vec<Result<u64, AuditError>> = claims.iter()
+    .map(|claim| audit_claim(&secp_inst, snapshot, nonce, claim))
+    .collect();
+
+  let positions: HashMap<(PublicKey, PublicKey, PublicKey, PublicKey), usize> = snapshot.accounts.iter().enumerate()
+    .map(|(i, account)| ((account.pubkey.x, account.pubkey.y, account.commitment.x, account.commitment.y), i))
+    .collect();
+
+  // (account, Pedersen commitment) -> claims publishing it, for claims over ledger accounts
+  let mut published: HashMap<(usize, PublicKey), Vec<usize>> = HashMap::new();
+  for (c, claim) in claims.iter().enumerate() {
+    if let Err(AuditError::Ledger(_)) = results[c] {
+      continue;
+    }
+    let proof = &claim.proof;
+    for i in 0..proof.pubkey_list.len() {
+      let entry = (proof.pubkey_list[i].x, proof.pubkey_list[i].y, proof.commitment_list[i].x, proof.commitment_list[i].y);
+      let account = positions[&entry];
+      published.entry((account, proof.pedersen_com_list[i])).or_default().push(c);
+    }
+  }
+
+  let mut double_claims: Vec<DoubleClaim> = published.into_iter()
+    .filter(|(_, exchanges)| exchanges.len() > 1)
+    .map(|((account, _), exchanges)| DoubleClaim { account, exchanges })
+    .collect();
+  double_claims.sort_by_key(|claim| claim.account);
+
+  AuditReport {
+    height: snapshot.height,
+    total_supply,
+    results,
+    double_claims,
+  }
+}
@@ -0,0 +1,102 @@
+extern crate structopt;
+extern crate quisquis;
+use structopt::StructOpt;
+
+use std::cmp;
+use std::process;
+use std::time::Instant;
+use rand::thread_rng;
+use quisquis::misc::Nonce;
+use quisquis::ledger::Ledger;
+use quisquis::dlog::DiscreteLogTable;
+use quisquis::nummatus_exchange::NummatusExchange;
+use quisquis::audit::{ReserveClaim, audit};
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "audit", about = "Audits several exchanges publishing Nummatus proofs over one simulated ledger.")]
+struct Opt {
+  /// Number of exchanges; the remaining owners of ledger accounts are users
+  #[structopt(short = "e", long = "exchanges", default_value = "3")]
+  num_exchanges: usize,
+  /// Number of owners of ledger accounts, exchanges included
+  #[structopt(short = "u", long = "owners", default_value = "6")]
+  num_owners: usize,
+  #[structopt(short = "b", long = "blocks", default_value = "5")]
+  num_blocks: u64,
+  #[structopt(short = "a", long = "accounts", default_value = "10")]
+  accounts_per_block: usize,
+  /// Transfers per block, run over as many blocks again after the accounts are created
+  #[structopt(short = "t", long = "transfers", default_value = "2")]
+  transfers_per_block: usize,
+  /// Make the second exchange also claim an account of the first
+  #[structopt(long = "double-claim")]
+  double_claim: bool,
+  /// Auditor nonce absorbed into every challenge, as 64 hex digits; random if omitted
+  #[structopt(long = "nonce")]
+  nonce: Option<Nonce>,
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    if opt.num_exchanges == 0 || opt.num_exchanges > opt.num_owners {
+      fail("--exchanges must be between 1 and --owners");
+    }
+    if opt.double_claim && opt.num_exchanges < 2 {
+      fail("--double-claim needs at least 2 exchanges");
+    }
+    if opt.num_blocks == 0 || opt.accounts_per_block == 0 {
+      fail("--blocks and --accounts must be non-zero, or the ledger has no accounts to audit");
+    }
+
+    let mut rng = thread_rng();
+    let mut ledger = Ledger::simulate(&mut rng, opt.num_owners, opt.num_blocks, opt.accounts_per_block);
+    ledger.simulate_transfers(&mut rng, opt.num_blocks, opt.transfers_per_block);
+
+    let height = ledger.height();
+    let snapshot = ledger.snapshot(height);
+    let table = DiscreteLogTable::new(cmp::max(1, ledger.total_supply(height)));  //no balance can exceed the supply
+    println!("Ledger height = {}, live accounts = {}, total supply = {}",
+      height, snapshot.accounts.len(), ledger.total_supply(height));
+
+    let nonce = opt.nonce.unwrap_or_else(|| Nonce::random(&mut rng));
+
+    let mut claims = Vec::new();
+    for owner in 0..opt.num_exchanges {
+      let mut keys: Vec<_> = ledger.owned_accounts(owner, height).iter().map(|&id| ledger.key(id)).collect();
+      if opt.double_claim && owner == 1 {
+        if let Some(&id) = ledger.owned_accounts(0, height).first() {
+          keys.push(ledger.key(id));
+        }
+      }
+
+      let gen_start = Instant::now();
+      let mut n_exch = NummatusExchange::from_keys(&snapshot, &keys, &table, 4).unwrap_or_else(|e| fail(&e.to_string()));
+      let proof = n_exch.generate_proof_with_nonce(Some(&nonce));
+      let (total, blinding) = n_exch.reserve_opening(&table).unwrap_or_else(|e| fail(&e.to_string()));
+      println!("Exchange {} claims {} in {:?}", owner, total, gen_start.elapsed());
+
+      claims.push(ReserveClaim { exchange: format!("exchange-{}", owner), proof, total, blinding });
+    }
+
+    let audit_start = Instant::now();
+    let report = audit(&snapshot, ledger.total_supply(height), Some(&nonce), &claims);
+    println!("Audit time = {:?}", audit_start.elapsed());
+
+    for (claim, result) in claims.iter().zip(&report.results) {
+      match result {
+        Ok(total) => println!("{}: verified reserves = {}", claim.exchange, total),
+        Err(e) => println!("{}: rejected, {}", claim.exchange, e),
+      }
+    }
+    for double_claim in &report.double_claims {
+      let names: Vec<&str> = double_claim.exchanges.iter().map(|&c| claims[c].exchange.as_str()).collect();
+      println!("Account {} is claimed by {}", double_claim.account, names.join(", "));
+    }
+    println!("Total verified reserves = {} of supply {}", report.total_reserves(), report.total_supply);
+    println!("Audit {}", if report.passed() { "passed" } else { "failed" });
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
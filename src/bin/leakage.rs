@@ -0,0 +1,33 @@
+extern crate structopt;
+extern crate quisquis;
+use structopt::StructOpt;
+
+use std::process;
+use std::time::Instant;
+use quisquis::leakage::{Samples, analyse};
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "leakage", about = "Tests whether Nummatus proofs reveal which outputs the exchange owns.")]
+struct Opt {
+  /// Number of exchanges to prove for
+  #[structopt(short = "r", long = "runs", default_value = "20")]
+  num_runs: usize,
+  #[structopt(short = "a", long = "anonsize", default_value = "100")]
+  anon_list_size: usize,
+  #[structopt(short = "o", long = "ownsize", default_value = "50")]
+  own_list_size: usize,
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    let sample_start = Instant::now();
+    let samples = Samples::collect(opt.num_runs, opt.anon_list_size, opt.own_list_size);
+    println!("Sampling time = {:?}", sample_start.elapsed());
+
+    let report = analyse(&samples);
+    println!("{}", report);
+    if report.leaks() {
+      process::exit(1);
+    }
+}
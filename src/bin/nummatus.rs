@@ -2,53 +2,83 @@ extern crate structopt;
 extern crate quisquis;
 use structopt::StructOpt;
 
-use std::time::{Instant, Duration};
-use quisquis::nummatus_exchange::NummatusExchange;
+use std::env;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::time::Instant;
+use quisquis::misc::Nonce;
+use quisquis::sweep::{SizeList, OutputFormat, parse_num_iter, measure_nummatus, write_rows, with_default_subcommand};
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "quisquis", about = "Quisquis_PoA proof generation simulator.")]
-struct Opt {
-  //#[structopt(short = "a", long = "anonsize")]
-  anon_list_size: usize,
-  //#[structopt(short = "o", long = "ownsize")]
-  own_list_size: usize,
-  #[structopt(short = "n", long = "numiter", default_value = "1")]
-  num_iter: u32,
+enum Opt {
+  /// Runs a single configuration and prints the average timings
+  #[structopt(name = "run")]
+  Run {
+    anon_list_size: usize,
+    own_list_size: usize,
+    #[structopt(short = "n", long = "numiter", default_value = "1", parse(try_from_str = "parse_num_iter"))]
+    num_iter: u32,
+    /// Use a single global challenge for all outputs
+    #[structopt(short = "c", long = "compact")]
+    compact: bool,
+    /// Auditor nonce absorbed into every challenge, as 64 hex digits
+    #[structopt(long = "nonce")]
+    nonce: Option<Nonce>,
+  },
+  /// Runs every combination of anonymity-set size and owned-list size
+  #[structopt(name = "sweep")]
+  Sweep {
+    /// Anonymity-set sizes, e.g. 100,1000,10000 or 100:1000:100
+    #[structopt(short = "a", long = "anonsize")]
+    anon_list_sizes: SizeList,
+    /// Owned-list sizes, e.g. 1,10,100 or 10:100:10; sizes above the anonymity-set size are skipped
+    #[structopt(short = "o", long = "ownsize")]
+    own_list_sizes: SizeList,
+    #[structopt(short = "n", long = "numiter", default_value = "1", parse(try_from_str = "parse_num_iter"))]
+    num_iter: u32,
+    /// Also measure the compact layout with a single global challenge
+    #[structopt(short = "c", long = "compact")]
+    compact: bool,
+    /// csv or json
+    #[structopt(short = "f", long = "format", default_value = "csv")]
+    format: OutputFormat,
+    /// Output file, standard output if omitted
+    #[structopt(long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Auditor nonce absorbed into every challenge, as 64 hex digits
+    #[structopt(long = "nonce")]
+    nonce: Option<Nonce>,
+  },
 }
 
 fn main() {
-    let opt = Opt::from_args();
+    let opt = Opt::from_iter(with_default_subcommand(env::args(), &["run", "sweep"]));
 
-    let num_iter = opt.num_iter;
-    let mut n_exch = NummatusExchange::new(opt.anon_list_size, opt.own_list_size);
-    let mut quisquis_proof;
-    let mut gen_proof_start;
-    let mut gen_proof_end;
-    let mut ver_proof_start;
-    let mut ver_proof_end;
-    let mut total_gen_proof_duration = Duration::new(0, 0);
-    let mut total_ver_proof_duration = Duration::new(0, 0);
-    
-    let sim_start = Instant::now();
+    match opt {
+      Opt::Run { anon_list_size, own_list_size, num_iter, compact, nonce } => {
+        let sim_start = Instant::now();
+        let row = measure_nummatus(anon_list_size, own_list_size, num_iter, compact, nonce.as_ref());
+        println!("Total simulation time = {:?}", sim_start.elapsed());
+        println!("{}", row);
+      },
+      Opt::Sweep { anon_list_sizes, own_list_sizes, num_iter, compact, format, output, nonce } => {
+        let mut rows = Vec::new();
+        for &anon_list_size in &anon_list_sizes.0 {
+          for &own_list_size in own_list_sizes.0.iter().filter(|&&own_list_size| own_list_size <= anon_list_size) {
+            eprintln!("Running anon_list_size = {}, own_list_size = {}", anon_list_size, own_list_size);
+            rows.push(measure_nummatus(anon_list_size, own_list_size, num_iter, false, nonce.as_ref()));
+            if compact {
+              rows.push(measure_nummatus(anon_list_size, own_list_size, num_iter, true, nonce.as_ref()));
+            }
+          }
+        }
 
-    for _i in 0..num_iter {
-      gen_proof_start = Instant::now();
-      quisquis_proof = n_exch.generate_proof();
-      gen_proof_end = Instant::now();
-      total_gen_proof_duration += gen_proof_end.duration_since(gen_proof_start);
-
-      ver_proof_start = Instant::now();
-      assert!(quisquis_proof.verify());
-      ver_proof_end = Instant::now();
-      total_ver_proof_duration += ver_proof_end.duration_since(ver_proof_start);
+        match output {
+          Some(path) => write_rows(&mut File::create(path).unwrap(), format, &rows).unwrap(),
+          None => write_rows(&mut io::stdout(), format, &rows).unwrap(),
+        }
+      },
     }
-
-    let sim_end = Instant::now();
-    println!("Total simulation time = {:?}", sim_end.duration_since(sim_start));
-
-    println!("Options = {:?}", opt);
-    println!("Average proof generation time = {:?}",
-      total_gen_proof_duration.checked_div(num_iter).unwrap());
-    println!("Average proof verification time = {:?}",
-      total_ver_proof_duration.checked_div(num_iter).unwrap());
-}
\ No newline at end of file
+}
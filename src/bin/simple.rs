@@ -2,50 +2,68 @@ extern crate structopt;
 extern crate quisquis;
 use structopt::StructOpt;
 
-use std::time::{Instant, Duration};
-use quisquis::simple_exchange::SimpleExchange;
+use std::env;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::time::Instant;
+use quisquis::misc::Nonce;
+use quisquis::sweep::{SizeList, OutputFormat, parse_num_iter, measure_simple, write_rows, with_default_subcommand};
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "simple", about = "Simple reserve proof generation simulator.")]
-struct Opt {
-  //#[structopt(short = "o", long = "ownsize")]
-  own_list_size: usize,
-  #[structopt(short = "n", long = "numiter", default_value = "1")]
-  num_iter: u32,
+enum Opt {
+  /// Runs a single configuration and prints the average timings
+  #[structopt(name = "run")]
+  Run {
+    own_list_size: usize,
+    #[structopt(short = "n", long = "numiter", default_value = "1", parse(try_from_str = "parse_num_iter"))]
+    num_iter: u32,
+    /// Auditor nonce absorbed into every challenge, as 64 hex digits
+    #[structopt(long = "nonce")]
+    nonce: Option<Nonce>,
+  },
+  /// Runs every owned-list size in the list
+  #[structopt(name = "sweep")]
+  Sweep {
+    /// Owned-list sizes, e.g. 25,50,75 or 250:2500:250
+    #[structopt(short = "o", long = "ownsize")]
+    own_list_sizes: SizeList,
+    #[structopt(short = "n", long = "numiter", default_value = "1", parse(try_from_str = "parse_num_iter"))]
+    num_iter: u32,
+    /// csv or json
+    #[structopt(short = "f", long = "format", default_value = "csv")]
+    format: OutputFormat,
+    /// Output file, standard output if omitted
+    #[structopt(long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Auditor nonce absorbed into every challenge, as 64 hex digits
+    #[structopt(long = "nonce")]
+    nonce: Option<Nonce>,
+  },
 }
 
 fn main() {
-    let opt = Opt::from_args();
+    let opt = Opt::from_iter(with_default_subcommand(env::args(), &["run", "sweep"]));
 
-    let num_iter = opt.num_iter;
-    let mut quisquis_exch = SimpleExchange::new(opt.own_list_size);
-    let mut simple_proof;
-    let mut gen_proof_start;
-    let mut gen_proof_end;
-    let mut ver_proof_start;
-    let mut ver_proof_end;
-    let mut total_gen_proof_duration = Duration::new(0, 0);
-    let mut total_ver_proof_duration = Duration::new(0, 0);
-    let sim_start = Instant::now();
-    for _i in 0..num_iter {
-      gen_proof_start = Instant::now();
-      simple_proof = quisquis_exch.generate_proof();
-      gen_proof_end = Instant::now();
-      total_gen_proof_duration += gen_proof_end.duration_since(gen_proof_start);
+    match opt {
+      Opt::Run { own_list_size, num_iter, nonce } => {
+        let sim_start = Instant::now();
+        let row = measure_simple(own_list_size, num_iter, nonce.as_ref());
+        println!("Total simulation time = {:?}", sim_start.elapsed());
+        println!("{}", row);
+      },
+      Opt::Sweep { own_list_sizes, num_iter, format, output, nonce } => {
+        let mut rows = Vec::new();
+        for &own_list_size in &own_list_sizes.0 {
+          eprintln!("Running own_list_size = {}", own_list_size);
+          rows.push(measure_simple(own_list_size, num_iter, nonce.as_ref()));
+        }
 
-      ver_proof_start = Instant::now();
-      assert!(simple_proof.verify());
-      ver_proof_end = Instant::now();
-      total_ver_proof_duration += ver_proof_end.duration_since(ver_proof_start);
+        match output {
+          Some(path) => write_rows(&mut File::create(path).unwrap(), format, &rows).unwrap(),
+          None => write_rows(&mut io::stdout(), format, &rows).unwrap(),
+        }
+      },
     }
-
-    let sim_end = Instant::now();
-    println!("Total simulation time = {:?}", sim_end.duration_since(sim_start));
-
-    println!("Options = {:?}", opt);
-    println!("Average proof generation time = {:?}",
-      total_gen_proof_duration.checked_div(num_iter).unwrap());
-    println!("Average proof verification time = {:?}",
-      total_ver_proof_duration.checked_div(num_iter).unwrap());
 }
-
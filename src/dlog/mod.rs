@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey};
+
+use crate::misc::QPublicKey;
+use crate::misc::GENERATOR_G;
+use crate::misc::MINUS_ONE_KEY;
+use crate::misc::amount_to_key;
+use crate::misc::single_base_product;
+
+// Baby-step giant-step table for discrete logs to base g in 1..=max_amount.
+// Building it costs about sqrt(max_amount) group operations and it can be
+// reused for every decryption with the same bound.
+pub struct DiscreteLogTable {
+  max_amount: u64,
+  step: u64,                                  //m = ceil(sqrt(max_amount))
+  baby_steps: HashMap<PublicKey, u64>,        //g^j -> j for j in 1..=m
+  giant_step: PublicKey,                      //g^-m
+}
+
+impl DiscreteLogTable {
+  pub fn new(max_amount: u64) -> DiscreteLogTable {
+    assert!(max_amount != 0);
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let g_basepoint = PublicKey::from_slice(&secp_inst, &GENERATOR_G).unwrap();
+
+    let mut step = (max_amount as f64).sqrt() as u64;
+    while step * step < max_amount {
+      step += 1;
+    }
+
+    let mut baby_steps = HashMap::with_capacity(step as usize);
+    let mut g_j = g_basepoint;
+    baby_steps.insert(g_j, 1);
+    for j in 2..=step {
+      g_j = PublicKey::from_combination(&secp_inst, vec![&g_j, &g_basepoint]).unwrap();
+      baby_steps.insert(g_j, j);
+    }
+
+    let mut minus_m = amount_to_key(&secp_inst, step);
+    minus_m.mul_assign(&secp_inst, &MINUS_ONE_KEY).unwrap();
+
+    DiscreteLogTable {
+      max_amount,
+      step,
+      baby_steps,
+      giant_step: single_base_product(&secp_inst, g_basepoint, minus_m),
+    }
+  }
+
+  pub fn max_amount(&self) -> u64 {
+    self.max_amount
+  }
+
+  // Returns v in 1..=max_amount with g^v = target, if there is one
+  pub fn solve(&self, target: PublicKey) -> Option<u64> {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut current = target;                                   // current = target * g^(-m*i)
+
+    for i in 0..=(self.max_amount / self.step) {
+      if let Some(&j) = self.baby_steps.get(&current) {
+        let amount = i * self.step + j;
+        return if amount <= self.max_amount { Some(amount) } else { None };
+      }
+      current = match PublicKey::from_combination(&secp_inst, vec![&current, &self.giant_step]) {
+        Ok(next) => next,
+        Err(_) => return None,                                  // reached the identity, target = g^(m*i)
+      };
+    }
+    None
+  }
+}
+
+// Decrypts a Quisquis commitment (c, d) = (c, g^v * c^k) with secret key k,
+// returning v if it is within the bound of the table. A zero amount is
+// detected directly since g^0 is the identity and has no encoding.
+pub fn decrypt_commitment(commitment: &QPublicKey, key: &SecretKey, table: &DiscreteLogTable) -> Option<u64> {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+  let c_k = single_base_product(&secp_inst, commitment.x, key.clone());
+  if c_k == commitment.y {
+    return Some(0);
+  }
+
+  let mut minus_c_k = c_k;
+  minus_c_k.mul_assign(&secp_inst, &MINUS_ONE_KEY).unwrap();
+  let g_v = PublicKey::from_combination(&secp_inst, vec![&commitment.y, &minus_c_k]).unwrap();   // g^v = d * c^-k
+
+  table.solve(g_v)
+}
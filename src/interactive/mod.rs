@@ -0,0 +1,509 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::mpsc;
+use std::thread;
+
+use rand::thread_rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey, ONE_KEY};
+
+use crate::misc::QPublicKey;
+use crate::misc::{a_minus_bx, checked_a_minus_bx};
+use crate::misc::{is_valid_point, is_valid_scalar};
+use crate::nummatus_nizk::{self, NummatusPoK, ProverState};
+use crate::simple_nizk;
+
+// Interactive (three-move) versions of the Simplus and Nummatus proofs. The
+// exchange sends its statement with the first messages, the auditor answers
+// with a random challenge and the exchange responds. The math is the same as
+// in the non-interactive proofs, only the challenge is drawn by the auditor
+// instead of hashed, so the transcript convinces no one but that auditor.
+
+// Why an interactive session was aborted
+#[derive(Debug)]
+pub enum ProtocolError {
+  // A message arrived in a state that does not expect it
+  UnexpectedMessage,
+  // The lists of a message do not line up with each other or with the commitment
+  LengthMismatch,
+  // A commitment carries a placeholder or off-curve point
+  InvalidPoint,
+  // The other party hung up before the session was over
+  ChannelClosed,
+}
+
+impl fmt::Display for ProtocolError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ProtocolError::UnexpectedMessage => write!(f, "message does not fit the state of the session"),
+      ProtocolError::LengthMismatch => write!(f, "message lists have mismatched lengths"),
+      ProtocolError::InvalidPoint => write!(f, "commitment contains an invalid point"),
+      ProtocolError::ChannelClosed => write!(f, "channel closed before the session was over"),
+    }
+  }
+}
+
+impl Error for ProtocolError {}
+
+// Exchange side of a session: commit once, then respond to one challenge
+pub trait InteractiveProver {
+  type Commitment;
+  type Response;
+
+  fn commit(&mut self) -> Result<Self::Commitment, ProtocolError>;
+  fn respond(&mut self, challenge: &SecretKey) -> Result<Self::Response, ProtocolError>;
+}
+
+// Auditor side of a session: answer the commitment with a challenge, then
+// accept or reject the response
+pub trait InteractiveVerifier {
+  type Commitment;
+  type Response;
+
+  fn challenge(&mut self, commitment: Self::Commitment) -> Result<SecretKey, ProtocolError>;
+  fn verify(&mut self, response: Self::Response) -> Result<bool, ProtocolError>;
+}
+
+// What travels from the prover to the verifier
+pub enum ProverMessage<C, R> {
+  Commitment(C),
+  Response(R),
+}
+
+// Runs one session with the prover on its own thread, talking to the
+// verifier over a pair of local channels
+pub fn run_session<P, V>(prover: P, verifier: &mut V) -> Result<bool, ProtocolError>
+  where P: InteractiveProver + Send + 'static,
+        P::Commitment: Send + 'static,
+        P::Response: Send + 'static,
+        V: InteractiveVerifier<Commitment = P::Commitment, Response = P::Response>,
+{
+  let (to_verifier, from_prover) = mpsc::channel::<ProverMessage<P::Commitment, P::Response>>();
+  let (to_prover, from_verifier) = mpsc::channel::<SecretKey>();
+
+  let handle = thread::spawn(move || -> Result<(), ProtocolError> {
+    let mut prover = prover;
+    let commitment = prover.commit()?;
+    to_verifier.send(ProverMessage::Commitment(commitment)).map_err(|_| ProtocolError::ChannelClosed)?;
+    let challenge = from_verifier.recv().map_err(|_| ProtocolError::ChannelClosed)?;
+    let response = prover.respond(&challenge)?;
+    to_verifier.send(ProverMessage::Response(response)).map_err(|_| ProtocolError::ChannelClosed)
+  });
+
+  let result = (|| {
+    let commitment = match from_prover.recv().map_err(|_| ProtocolError::ChannelClosed)? {
+      ProverMessage::Commitment(commitment) => commitment,
+      ProverMessage::Response(_) => return Err(ProtocolError::UnexpectedMessage),
+    };
+    let challenge = verifier.challenge(commitment)?;
+    to_prover.send(challenge).map_err(|_| ProtocolError::ChannelClosed)?;
+    match from_prover.recv().map_err(|_| ProtocolError::ChannelClosed)? {
+      ProverMessage::Response(response) => verifier.verify(response),
+      ProverMessage::Commitment(_) => Err(ProtocolError::UnexpectedMessage),
+    }
+  })();
+  drop(to_prover);
+
+  // an error on the prover side explains a closed channel on ours
+  let prover_result = handle.join().expect("prover thread panicked");
+  match (result, prover_result) {
+    (Err(ProtocolError::ChannelClosed), Err(e)) => Err(e),
+    (result, _) => result,
+  }
+}
+
+//a fresh random challenge for one session
+fn draw_challenge(secp_inst: &Secp256k1) -> SecretKey {
+  SecretKey::new(secp_inst, &mut thread_rng())
+}
+
+// First move of an interactive Nummatus session: the statement and the first
+// messages (v1, v2, v3) of every output
+pub struct NummatusCommitment {
+  pub h_basepoint: PublicKey,                 //h which is computed at height j of Quisquis blockchain
+  pub pubkey_list: Vec<QPublicKey>,           //Publickey for Quisquis
+  pub commitment_list: Vec<QPublicKey>,       //Quisquis commitment
+  pub pedersen_com_list: Vec<PublicKey>,      //Pedersen commitment
+  pub first_messages: Vec<(PublicKey, PublicKey, PublicKey)>,
+}
+
+// Third move of an interactive Nummatus session: each output's split of the
+// challenge and its responses
+pub struct NummatusResponse {
+  pub pok_list: Vec<NummatusPoK>,
+}
+
+enum NummatusProverStage {
+  Ready,
+  Committed(Vec<ProverState>),
+  Done,
+}
+
+pub struct NummatusProver {
+  h_basepoint: PublicKey,
+  pubkey_list: Vec<QPublicKey>,
+  commitment_list: Vec<QPublicKey>,
+  pedersen_com_list: Vec<PublicKey>,
+  own_keys: Vec<SecretKey>,                   //ZERO_KEY for decoys
+  decoy_keys: Vec<SecretKey>,                 //Pedersen blinding factors of the decoys
+  stage: NummatusProverStage,
+}
+
+impl NummatusProver {
+  pub fn new(
+    h_basepoint: PublicKey,
+    pubkey_list: Vec<QPublicKey>,
+    commitment_list: Vec<QPublicKey>,
+    pedersen_com_list: Vec<PublicKey>,
+    own_keys: Vec<SecretKey>,
+    decoy_keys: Vec<SecretKey>,
+  ) -> NummatusProver {
+    NummatusProver {
+      h_basepoint,
+      pubkey_list,
+      commitment_list,
+      pedersen_com_list,
+      own_keys,
+      decoy_keys,
+      stage: NummatusProverStage::Ready,
+    }
+  }
+}
+
+impl InteractiveProver for NummatusProver {
+  type Commitment = NummatusCommitment;
+  type Response = NummatusResponse;
+
+  fn commit(&mut self) -> Result<NummatusCommitment, ProtocolError> {
+    if !matches!(self.stage, NummatusProverStage::Ready) {
+      return Err(ProtocolError::UnexpectedMessage);
+    }
+    let n = self.pubkey_list.len();
+    if self.commitment_list.len() != n || self.pedersen_com_list.len() != n
+        || self.own_keys.len() != n || self.decoy_keys.len() != n {
+      return Err(ProtocolError::LengthMismatch);
+    }
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut rng = thread_rng();
+    let mut first_messages = Vec::with_capacity(n);
+    let mut states = Vec::with_capacity(n);
+    for i in 0..n {
+      let (v, state) = nummatus_nizk::first_message(
+                         &secp_inst,
+                         &mut rng,
+                         self.pubkey_list[i],
+                         self.commitment_list[i],
+                         self.pedersen_com_list[i],
+                         self.h_basepoint,
+                         &self.own_keys[i],
+                         &self.decoy_keys[i],
+                       );
+      first_messages.push(v);
+      states.push(state);
+    }
+    self.stage = NummatusProverStage::Committed(states);
+
+    Ok(NummatusCommitment {
+      h_basepoint: self.h_basepoint,
+      pubkey_list: self.pubkey_list.clone(),
+      commitment_list: self.commitment_list.clone(),
+      pedersen_com_list: self.pedersen_com_list.clone(),
+      first_messages,
+    })
+  }
+
+  fn respond(&mut self, challenge: &SecretKey) -> Result<NummatusResponse, ProtocolError> {
+    let states = match std::mem::replace(&mut self.stage, NummatusProverStage::Done) {
+      NummatusProverStage::Committed(states) => states,
+      stage => {
+        self.stage = stage;
+        return Err(ProtocolError::UnexpectedMessage);
+      },
+    };
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let pok_list = states.into_iter()
+      .map(|state| nummatus_nizk::response(&secp_inst, state, challenge))
+      .collect();
+    Ok(NummatusResponse { pok_list })
+  }
+}
+
+enum NummatusVerifierStage {
+  AwaitingCommitment,
+  AwaitingResponse(NummatusCommitment, SecretKey),
+  Done,
+}
+
+pub struct NummatusVerifier {
+  stage: NummatusVerifierStage,
+}
+
+impl NummatusVerifier {
+  pub fn new() -> NummatusVerifier {
+    NummatusVerifier { stage: NummatusVerifierStage::AwaitingCommitment }
+  }
+}
+
+impl Default for NummatusVerifier {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl InteractiveVerifier for NummatusVerifier {
+  type Commitment = NummatusCommitment;
+  type Response = NummatusResponse;
+
+  fn challenge(&mut self, commitment: NummatusCommitment) -> Result<SecretKey, ProtocolError> {
+    if !matches!(self.stage, NummatusVerifierStage::AwaitingCommitment) {
+      return Err(ProtocolError::UnexpectedMessage);
+    }
+    let n = commitment.pubkey_list.len();
+    if n == 0 || commitment.commitment_list.len() != n || commitment.pedersen_com_list.len() != n
+        || commitment.first_messages.len() != n {
+      return Err(ProtocolError::LengthMismatch);
+    }
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut points = vec![commitment.h_basepoint];
+    for i in 0..n {
+      let (v1, v2, v3) = commitment.first_messages[i];
+      points.extend_from_slice(&[commitment.pubkey_list[i].x, commitment.pubkey_list[i].y, commitment.commitment_list[i].x,
+                                 commitment.commitment_list[i].y, commitment.pedersen_com_list[i], v1, v2, v3]);
+    }
+    if !points.iter().all(|point| is_valid_point(&secp_inst, point)) {
+      return Err(ProtocolError::InvalidPoint);
+    }
+
+    let challenge = draw_challenge(&secp_inst);
+    self.stage = NummatusVerifierStage::AwaitingResponse(commitment, challenge.clone());
+    Ok(challenge)
+  }
+
+  fn verify(&mut self, response: NummatusResponse) -> Result<bool, ProtocolError> {
+    let (commitment, challenge) = match std::mem::replace(&mut self.stage, NummatusVerifierStage::Done) {
+      NummatusVerifierStage::AwaitingResponse(commitment, challenge) => (commitment, challenge),
+      stage => {
+        self.stage = stage;
+        return Err(ProtocolError::UnexpectedMessage);
+      },
+    };
+    if response.pok_list.len() != commitment.pubkey_list.len() {
+      return Err(ProtocolError::LengthMismatch);
+    }
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    for (i, rpok) in response.pok_list.iter().enumerate() {
+      // Out-of-range scalars never verify
+      let scalars = [&rpok.e1, &rpok.e2, &rpok.s1, &rpok.s2];
+      if !scalars.iter().all(|scalar| is_valid_scalar(&secp_inst, scalar)) {
+        return Ok(false);
+      }
+      // e1 + e2 must add up to the challenge, and e1 = e leaves no valid e2
+      if checked_a_minus_bx(&secp_inst, challenge.clone(), ONE_KEY, rpok.e1.clone()).as_ref() != Some(&rpok.e2) {
+        return Ok(false);
+      }
+      let v = nummatus_nizk::recompute_first_message(
+                &secp_inst,
+                commitment.pubkey_list[i],
+                commitment.commitment_list[i],
+                commitment.pedersen_com_list[i],
+                commitment.h_basepoint,
+                rpok,
+              );
+      if v != Some(commitment.first_messages[i]) {
+        return Ok(false);
+      }
+    }
+    Ok(true)
+  }
+}
+
+// First move of an interactive Simplus session: the statement and the first
+// messages (v1, v2) of every owned output
+pub struct SimpleCommitment {
+  pub h_basepoint: PublicKey,                 //h which is computed at height j of Quisquis blockchain
+  pub pubkey_list: Vec<QPublicKey>,           //Quisquis PublicKey
+  pub commitment_list: Vec<QPublicKey>,       //Quisquis commitment
+  pub pederson_list: Vec<PublicKey>,          //Pedersen commitment
+  pub first_messages: Vec<(PublicKey, PublicKey)>,
+}
+
+// Third move of an interactive Simplus session: one response s = r - e*k per output
+pub struct SimpleResponse {
+  pub responses: Vec<SecretKey>,
+}
+
+enum SimpleProverStage {
+  Ready,
+  Committed(Vec<SecretKey>),
+  Done,
+}
+
+pub struct SimpleProver {
+  h_basepoint: PublicKey,
+  pubkey_list: Vec<QPublicKey>,
+  commitment_list: Vec<QPublicKey>,
+  pederson_list: Vec<PublicKey>,
+  own_keys: Vec<SecretKey>,
+  stage: SimpleProverStage,
+}
+
+impl SimpleProver {
+  pub fn new(
+    h_basepoint: PublicKey,
+    pubkey_list: Vec<QPublicKey>,
+    commitment_list: Vec<QPublicKey>,
+    pederson_list: Vec<PublicKey>,
+    own_keys: Vec<SecretKey>,
+  ) -> SimpleProver {
+    SimpleProver {
+      h_basepoint,
+      pubkey_list,
+      commitment_list,
+      pederson_list,
+      own_keys,
+      stage: SimpleProverStage::Ready,
+    }
+  }
+}
+
+impl InteractiveProver for SimpleProver {
+  type Commitment = SimpleCommitment;
+  type Response = SimpleResponse;
+
+  fn commit(&mut self) -> Result<SimpleCommitment, ProtocolError> {
+    if !matches!(self.stage, SimpleProverStage::Ready) {
+      return Err(ProtocolError::UnexpectedMessage);
+    }
+    let n = self.pubkey_list.len();
+    if self.commitment_list.len() != n || self.pederson_list.len() != n || self.own_keys.len() != n {
+      return Err(ProtocolError::LengthMismatch);
+    }
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut rng = thread_rng();
+    let mut first_messages = Vec::with_capacity(n);
+    let mut randomness = Vec::with_capacity(n);
+    for i in 0..n {
+      let (v, r1) = simple_nizk::first_message(&secp_inst, &mut rng, self.pubkey_list[i], self.commitment_list[i], self.h_basepoint);
+      first_messages.push(v);
+      randomness.push(r1);
+    }
+    self.stage = SimpleProverStage::Committed(randomness);
+
+    Ok(SimpleCommitment {
+      h_basepoint: self.h_basepoint,
+      pubkey_list: self.pubkey_list.clone(),
+      commitment_list: self.commitment_list.clone(),
+      pederson_list: self.pederson_list.clone(),
+      first_messages,
+    })
+  }
+
+  fn respond(&mut self, challenge: &SecretKey) -> Result<SimpleResponse, ProtocolError> {
+    let randomness = match std::mem::replace(&mut self.stage, SimpleProverStage::Done) {
+      SimpleProverStage::Committed(randomness) => randomness,
+      stage => {
+        self.stage = stage;
+        return Err(ProtocolError::UnexpectedMessage);
+      },
+    };
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let responses = randomness.into_iter().zip(&self.own_keys)
+      .map(|(r1, key)| a_minus_bx(&secp_inst, r1, challenge.clone(), key.clone()))     // s = r1 - e*k
+      .collect();
+    Ok(SimpleResponse { responses })
+  }
+}
+
+enum SimpleVerifierStage {
+  AwaitingCommitment,
+  AwaitingResponse(SimpleCommitment, SecretKey),
+  Done,
+}
+
+pub struct SimpleVerifier {
+  stage: SimpleVerifierStage,
+}
+
+impl SimpleVerifier {
+  pub fn new() -> SimpleVerifier {
+    SimpleVerifier { stage: SimpleVerifierStage::AwaitingCommitment }
+  }
+}
+
+impl Default for SimpleVerifier {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl InteractiveVerifier for SimpleVerifier {
+  type Commitment = SimpleCommitment;
+  type Response = SimpleResponse;
+
+  fn challenge(&mut self, commitment: SimpleCommitment) -> Result<SecretKey, ProtocolError> {
+    if !matches!(self.stage, SimpleVerifierStage::AwaitingCommitment) {
+      return Err(ProtocolError::UnexpectedMessage);
+    }
+    let n = commitment.pubkey_list.len();
+    if n == 0 || commitment.commitment_list.len() != n || commitment.pederson_list.len() != n
+        || commitment.first_messages.len() != n {
+      return Err(ProtocolError::LengthMismatch);
+    }
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut points = vec![commitment.h_basepoint];
+    for i in 0..n {
+      let (v1, v2) = commitment.first_messages[i];
+      points.extend_from_slice(&[commitment.pubkey_list[i].x, commitment.pubkey_list[i].y, commitment.commitment_list[i].x,
+                                 commitment.commitment_list[i].y, commitment.pederson_list[i], v1, v2]);
+    }
+    if !points.iter().all(|point| is_valid_point(&secp_inst, point)) {
+      return Err(ProtocolError::InvalidPoint);
+    }
+
+    let challenge = draw_challenge(&secp_inst);
+    self.stage = SimpleVerifierStage::AwaitingResponse(commitment, challenge.clone());
+    Ok(challenge)
+  }
+
+  fn verify(&mut self, response: SimpleResponse) -> Result<bool, ProtocolError> {
+    let (commitment, challenge) = match std::mem::replace(&mut self.stage, SimpleVerifierStage::Done) {
+      SimpleVerifierStage::AwaitingResponse(commitment, challenge) => (commitment, challenge),
+      stage => {
+        self.stage = stage;
+        return Err(ProtocolError::UnexpectedMessage);
+      },
+    };
+    if response.responses.len() != commitment.pubkey_list.len() {
+      return Err(ProtocolError::LengthMismatch);
+    }
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    for (i, s) in response.responses.iter().enumerate() {
+      // Out-of-range scalars never verify
+      if !is_valid_scalar(&secp_inst, s) {
+        return Ok(false);
+      }
+      let v = simple_nizk::recompute_first_message(
+                &secp_inst,
+                commitment.pubkey_list[i],
+                commitment.commitment_list[i],
+                commitment.pederson_list[i],
+                commitment.h_basepoint,
+                &challenge,
+                s,
+              );
+      if v != Some(commitment.first_messages[i]) {
+        return Ok(false);
+      }
+    }
+    Ok(true)
+  }
+}
@@ -0,0 +1,112 @@
+use digest::Digest;
+use rand::Rng;
+use sha2::Sha256;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::SecretKey;
+
+use crate::ledger::LedgerSnapshot;
+use crate::dlog::DiscreteLogTable;
+use crate::scan::{ScanMatch, scan_parallel};
+
+pub const SEED_SIZE: usize = 32;
+
+// Domain separators so keychain hashes never collide with each other or
+// with other uses of SHA-256 on the seed
+const KEYCHAIN_TAG: &[u8] = b"nummatus/keychain/v1";
+const CHILD_TAG: &[u8] = b"nummatus/keychain/child/v1";
+
+// Deterministic account keys for an exchange. Keychains form a tree: the
+// seed of child keychain i is a hash of its parent's seed and i, and account
+// key i of a keychain is a hash of its seed and i. An exchange can give each
+// purpose (e.g. hot and cold wallets) its own branch, and backing up the
+// master seed is enough to regenerate every branch and account key. The
+// accounts themselves are found again by scanning the ledger with the keys.
+// Derivation is hardened only: a child seed or key reveals nothing about its
+// parent or siblings, but there are no public child keys.
+#[derive (Clone)]
+pub struct Keychain {
+  seed: [u8; SEED_SIZE],
+}
+
+impl Keychain {
+  pub fn from_seed(seed: [u8; SEED_SIZE]) -> Keychain {
+    Keychain { seed }
+  }
+
+  pub fn random<R: Rng>(rng: &mut R) -> Keychain {
+    let mut seed = [0u8; SEED_SIZE];
+    rng.fill(&mut seed);
+    Keychain { seed }
+  }
+
+  pub fn seed(&self) -> [u8; SEED_SIZE] {
+    self.seed
+  }
+
+  // Child keychain index, with seed SHA-256(child tag || seed || index)
+  pub fn child(&self, index: u32) -> Keychain {
+    let mut hasher = Sha256::new();
+    hasher.input(CHILD_TAG);
+    hasher.input(self.seed);
+    hasher.input(index.to_be_bytes());
+
+    let mut seed = [0u8; SEED_SIZE];
+    seed.copy_from_slice(&hasher.result());
+    Keychain { seed }
+  }
+
+  // Keychain at path below this one, e.g. [1, 0] is child(1).child(0)
+  pub fn derive_path(&self, path: &[u32]) -> Keychain {
+    path.iter().fold(self.clone(), |keychain, &index| keychain.child(index))
+  }
+
+  // Account key index, SHA-256(tag || seed || index || counter) for the first
+  // counter giving a valid non-zero scalar. Almost always counter = 0.
+  pub fn derive_key(&self, index: u32) -> SecretKey {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut counter = 0u32;
+
+    loop {
+      let mut hasher = Sha256::new();
+      hasher.input(KEYCHAIN_TAG);
+      hasher.input(self.seed);
+      hasher.input(index.to_be_bytes());
+      hasher.input(counter.to_be_bytes());
+
+      if let Ok(key) = SecretKey::from_slice(&secp_inst, &hasher.result()) {
+        break key;
+      }
+      counter += 1;
+    }
+  }
+
+  // Account keys start..start + count, stopping at the end of the index range
+  pub fn derive_keys(&self, start: u32, count: u32) -> Vec<SecretKey> {
+    (start..start.saturating_add(count)).map(|index| self.derive_key(index)).collect()
+  }
+
+  // Finds the accounts of the snapshot owned by account keys, deriving keys in
+  // order until gap_limit consecutive indices after the last used one own no
+  // account. Keys must therefore be handed out without leaving larger gaps.
+  pub fn scan(&self, snapshot: &LedgerSnapshot, table: &DiscreteLogTable, gap_limit: u32, num_threads: usize) -> Vec<ScanMatch> {
+    assert!(gap_limit != 0);
+
+    let mut matches = Vec::new();
+    let mut scanned = 0u32;                                     // keys 0..scanned have been tried
+    let mut used_end = 0u32;                                    // one past the highest index that owns an account
+
+    while scanned < used_end.saturating_add(gap_limit) {
+      let keys = self.derive_keys(scanned, used_end.saturating_add(gap_limit) - scanned);
+      for found in scan_parallel(snapshot, &keys, table, num_threads) {
+        let position = keys.iter().position(|key| *key == found.key).unwrap() as u32;
+        used_end = used_end.max(scanned + position + 1);
+        matches.push(found);
+      }
+      scanned += keys.len() as u32;
+    }
+
+    matches.sort_by_key(|found| found.index);
+    matches
+  }
+}
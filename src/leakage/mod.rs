@@ -0,0 +1,220 @@
+use std::fmt;
+use std::time::Duration;
+
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+
+use crate::misc::{POINT_SIZE, SCALAR_SIZE};
+use crate::misc::serialize_point;
+use crate::nummatus_exchange::NummatusExchange;
+
+// Statistical tests on what a Nummatus proof publishes per output, comparing
+// owned outputs against decoys. A proof that hides ownership gives the same
+// distributions for both, so every statistic stays close to zero; one above
+// LEAK_THRESHOLD means an observer can tell the two apart.
+
+// |t| or |z| above which a single comparison reports a leak, as in dudect.
+// The bit tests take the largest statistic over all bit positions of a
+// field, so their threshold is raised with bit_threshold to keep the same
+// false positive rate for the whole field.
+pub const LEAK_THRESHOLD: f64 = 4.5;
+
+// Share of the slowest pooled timings discarded before the timing test, to
+// keep scheduler and allocator hiccups from dominating the variance
+const TIMING_CROP: f64 = 0.1;
+
+// What one output of a proof reveals, with the time its PoK took
+#[derive(Clone)]
+pub struct OutputSample {
+  pub e1: [u8; SCALAR_SIZE],
+  pub e2: [u8; SCALAR_SIZE],
+  pub s1: [u8; SCALAR_SIZE],
+  pub s2: [u8; SCALAR_SIZE],
+  pub pedersen_com: [u8; POINT_SIZE],         //compressed Pedersen commitment
+  pub time: Duration,                         //time to create its PoK
+}
+
+// Output samples of several proofs, split by whether the exchange owns the output
+#[derive(Default)]
+pub struct Samples {
+  pub owned: Vec<OutputSample>,
+  pub decoy: Vec<OutputSample>,
+}
+
+impl Samples {
+  // Runs num_runs fresh exchanges of the given size and records every output of their proofs
+  pub fn collect(num_runs: usize, anon_list_size: usize, own_list_size: usize) -> Samples {
+    assert!(own_list_size != 0 && own_list_size < anon_list_size);
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut samples = Samples::default();
+
+    for _ in 0..num_runs {
+      let mut n_exch = NummatusExchange::new(anon_list_size, own_list_size);
+      let owned = n_exch.owned_indices();
+      let (proof, timings) = n_exch.generate_proof_with_timings(None);
+
+      for (i, &time) in timings.iter().enumerate() {
+        let mut pok = Vec::with_capacity(4 * SCALAR_SIZE);
+        proof.pok_list[i].write_to(&mut pok).unwrap();
+        let mut pedersen_com = [0u8; POINT_SIZE];
+        pedersen_com.copy_from_slice(&serialize_point(&secp_inst, &proof.pedersen_com_list[i], true));
+
+        let sample = OutputSample {
+          e1: scalar_at(&pok, 0),
+          e2: scalar_at(&pok, 1),
+          s1: scalar_at(&pok, 2),
+          s2: scalar_at(&pok, 3),
+          pedersen_com,
+          time,
+        };
+        if owned.binary_search(&i).is_ok() {
+          samples.owned.push(sample);
+        } else {
+          samples.decoy.push(sample);
+        }
+      }
+    }
+    samples
+  }
+}
+
+//the k-th scalar of a serialized NummatusPoK
+fn scalar_at(pok: &[u8], k: usize) -> [u8; SCALAR_SIZE] {
+  let mut scalar = [0u8; SCALAR_SIZE];
+  scalar.copy_from_slice(&pok[k * SCALAR_SIZE..(k + 1) * SCALAR_SIZE]);
+  scalar
+}
+
+// Result of one distribution test
+#[derive(Debug, Clone)]
+pub struct LeakageTest {
+  pub name: &'static str,
+  pub statistic: f64,
+  pub threshold: f64,                         //|statistic| above which the test reports a leak
+}
+
+impl LeakageTest {
+  pub fn leaks(&self) -> bool {
+    self.statistic.abs() > self.threshold
+  }
+}
+
+pub struct LeakageReport {
+  pub owned_samples: usize,
+  pub decoy_samples: usize,
+  pub owned_time: Duration,                   //mean PoK time of owned outputs, after cropping
+  pub decoy_time: Duration,                   //mean PoK time of decoys, after cropping
+  pub tests: Vec<LeakageTest>,
+}
+
+impl LeakageReport {
+  pub fn leaks(&self) -> bool {
+    self.tests.iter().any(|test| test.leaks())
+  }
+}
+
+impl fmt::Display for LeakageReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "Owned outputs = {}, decoy outputs = {}", self.owned_samples, self.decoy_samples)?;
+    writeln!(f, "Mean PoK time: owned = {:?}, decoy = {:?}", self.owned_time, self.decoy_time)?;
+    for test in &self.tests {
+      writeln!(f, "{:<14} {:>8.2} / {:.2}  {}", test.name, test.statistic, test.threshold, if test.leaks() { "LEAK" } else { "ok" })?;
+    }
+    write!(f, "{}", if self.leaks() { "Ownership leaks" } else { "No leak detected" })
+  }
+}
+
+// Runs every test on the samples: per-bit frequencies of each published
+// scalar and of the Pedersen commitment encoding, and the PoK timings
+pub fn analyse(samples: &Samples) -> LeakageReport {
+  assert!(!samples.owned.is_empty() && !samples.decoy.is_empty());
+
+  let bit_test = |name: &'static str, field: fn(&OutputSample) -> &[u8]| LeakageTest {
+    name,
+    statistic: max_bit_z(
+      &samples.owned.iter().map(field).collect::<Vec<&[u8]>>(),
+      &samples.decoy.iter().map(field).collect::<Vec<&[u8]>>(),
+    ),
+    threshold: bit_threshold(8 * field(&samples.owned[0]).len()),
+  };
+
+  let times = |set: &[OutputSample]| set.iter().map(|sample| sample.time.as_nanos() as f64).collect::<Vec<f64>>();
+  let (owned_times, decoy_times) = crop(&times(&samples.owned), &times(&samples.decoy), TIMING_CROP);
+
+  let mean = |xs: &[f64]| Duration::from_nanos((xs.iter().sum::<f64>() / xs.len() as f64) as u64);
+
+  LeakageReport {
+    owned_samples: samples.owned.len(),
+    decoy_samples: samples.decoy.len(),
+    owned_time: mean(&owned_times),
+    decoy_time: mean(&decoy_times),
+    tests: vec![
+      bit_test("e1 bits", |sample| &sample.e1),
+      bit_test("e2 bits", |sample| &sample.e2),
+      bit_test("s1 bits", |sample| &sample.s1),
+      bit_test("s2 bits", |sample| &sample.s2),
+      bit_test("p encoding", |sample| &sample.pedersen_com),
+      LeakageTest { name: "pok time", statistic: welch_t(&owned_times, &decoy_times), threshold: LEAK_THRESHOLD },
+    ],
+  }
+}
+
+// Two-proportion z statistic for x1 successes out of n1 against x2 out of
+// n2; zero when both proportions are 0 or 1
+pub fn two_proportion_z(x1: usize, n1: usize, x2: usize, n2: usize) -> f64 {
+  let (p1, p2) = (x1 as f64 / n1 as f64, x2 as f64 / n2 as f64);
+  let pooled = (x1 + x2) as f64 / (n1 + n2) as f64;
+  let se = (pooled * (1.0 - pooled) * (1.0 / n1 as f64 + 1.0 / n2 as f64)).sqrt();
+  if se == 0.0 { 0.0 } else { (p1 - p2) / se }
+}
+
+// Bonferroni-adjusted threshold for the largest |z| over num_bits
+// comparisons: the smallest z whose two-sided normal tail, times num_bits,
+// is at most the tail of a single comparison at LEAK_THRESHOLD. Tails are
+// taken from the bound P(|Z| > z) <= 2 phi(z) / z, found by bisection.
+pub fn bit_threshold(num_bits: usize) -> f64 {
+  let tail = |z: f64| 2.0 * (-z * z / 2.0).exp() / ((2.0 * std::f64::consts::PI).sqrt() * z);
+  let target = tail(LEAK_THRESHOLD) / num_bits as f64;
+
+  let (mut low, mut high) = (LEAK_THRESHOLD, 2.0 * LEAK_THRESHOLD);
+  for _ in 0..64 {
+    let mid = (low + high) / 2.0;
+    if tail(mid) > target { low = mid } else { high = mid }
+  }
+  high
+}
+
+// Largest |z| over all bit positions of two sets of equally long byte strings
+pub fn max_bit_z(a: &[&[u8]], b: &[&[u8]]) -> f64 {
+  let len = a[0].len();
+  let ones = |set: &[&[u8]], bit: usize| set.iter().filter(|bytes| bytes[bit / 8] >> (7 - bit % 8) & 1 == 1).count();
+
+  (0..8 * len)
+    .map(|bit| two_proportion_z(ones(a, bit), a.len(), ones(b, bit), b.len()).abs())
+    .fold(0.0, f64::max)
+}
+
+// Welch's t statistic for the difference of the means of a and b
+pub fn welch_t(a: &[f64], b: &[f64]) -> f64 {
+  let mean_var = |xs: &[f64]| {
+    let n = xs.len() as f64;
+    let mean = xs.iter().sum::<f64>() / n;
+    let var = xs.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1.0);
+    (mean, var, n)
+  };
+  let (mean_a, var_a, n_a) = mean_var(a);
+  let (mean_b, var_b, n_b) = mean_var(b);
+  let se = (var_a / n_a + var_b / n_b).sqrt();
+  if se == 0.0 { 0.0 } else { (mean_a - mean_b) / se }
+}
+
+//drops the values of both sets above the (1 - fraction) quantile of their union
+fn crop(a: &[f64], b: &[f64], fraction: f64) -> (Vec<f64>, Vec<f64>) {
+  let mut pooled: Vec<f64> = a.iter().chain(b).cloned().collect();
+  pooled.sort_by(|x, y| x.partial_cmp(y).unwrap());
+  let cutoff = pooled[((pooled.len() - 1) as f64 * (1.0 - fraction)) as usize];
+
+  let keep = |xs: &[f64]| xs.iter().cloned().filter(|&x| x <= cutoff).collect::<Vec<f64>>();
+  (keep(a), keep(b))
+}
@@ -0,0 +1,256 @@
+use rand::Rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey};
+
+use crate::misc::QPublicKey;
+use crate::misc::GENERATOR_G;
+use crate::misc::MAX_AMOUNT_PER_OUTPUT;
+use crate::misc::amount_to_key;
+
+use crate::update_nizk::AccountUpdate;
+
+// An account as it appears on the Quisquis ledger. Only the public key and
+// the ElGamal-style commitment to the balance are public.
+#[derive (Copy, Clone)]
+pub struct LedgerAccount {
+  pub id: usize,                              //position of the account in the ledger
+  pub pubkey: QPublicKey,                     //(g^r, g^(r*k)) for secret key k
+  pub commitment: QPublicKey,                 //(x^r2, g^v * y^r2) for balance v
+  pub created: u64,                           //height of the block that created it
+}
+
+// The accounts alive at a given height, which is what a verifier at that height sees
+pub struct LedgerSnapshot {
+  pub height: u64,
+  pub accounts: Vec<LedgerAccount>,
+}
+
+// Simulated Quisquis ledger. Besides the public accounts it keeps the ground
+// truth a real ledger would not reveal (owner, secret key and balance of each
+// account) so that exchanges can be built from it and results checked.
+pub struct Ledger {
+  accounts: Vec<LedgerAccount>,
+  spent: Vec<Option<u64>>,                    //height at which each account was spent, if any
+  owners: Vec<usize>,
+  keys: Vec<SecretKey>,
+  balances: Vec<u64>,
+  height: u64,
+  g_basepoint: PublicKey,
+}
+
+// Creates the public key and commitment of a fresh account with secret key k and balance v
+pub fn create_account<R: Rng>(secp_inst: &Secp256k1, rng: &mut R, key: &SecretKey, balance: u64) -> (QPublicKey, QPublicKey) {
+  let g_basepoint = PublicKey::from_slice(secp_inst, &GENERATOR_G).unwrap();
+  let r1 = SecretKey::new(secp_inst, rng);
+  let r2 = SecretKey::new(secp_inst, rng);
+
+  let mut pubkey = QPublicKey::new();
+  pubkey.x = g_basepoint;     //generating PublicKey from SecretKey
+  pubkey.x.mul_assign(secp_inst, &r1).unwrap();
+  pubkey.y = pubkey.x;
+  pubkey.y.mul_assign(secp_inst, key).unwrap();
+
+  let mut commitment = QPublicKey::new();
+  commitment.x = pubkey.x;    //generating commitment from PublicKey and amount
+  commitment.x.mul_assign(secp_inst, &r2).unwrap();
+  let mut r2_d = pubkey.y;
+  r2_d.mul_assign(secp_inst, &r2).unwrap();
+  commitment.y = if balance == 0 {
+    r2_d                      //g^0 is the identity
+  } else {
+    let mut v_g = g_basepoint;
+    v_g.mul_assign(secp_inst, &amount_to_key(secp_inst, balance)).unwrap();
+    PublicKey::from_combination(secp_inst, vec![&v_g, &r2_d]).unwrap()
+  };
+
+  (pubkey, commitment)
+}
+
+impl Default for Ledger {
+  fn default() -> Ledger {
+    Ledger::new()
+  }
+}
+
+impl Ledger {
+  pub fn new() -> Ledger {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+    Ledger {
+      accounts: Vec::new(),
+      spent: Vec::new(),
+      owners: Vec::new(),
+      keys: Vec::new(),
+      balances: Vec::new(),
+      height: 0,
+      g_basepoint: PublicKey::from_slice(&secp_inst, &GENERATOR_G).unwrap(),
+    }
+  }
+
+  // Builds a ledger of num_blocks blocks, each creating accounts_per_block
+  // accounts with a uniformly random owner in 0..num_owners and balance
+  pub fn simulate<R: Rng>(rng: &mut R, num_owners: usize, num_blocks: u64, accounts_per_block: usize) -> Ledger {
+    assert!(num_owners != 0);
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut ledger = Ledger::new();
+
+    for _block in 0..num_blocks {
+      ledger.advance();
+      for _i in 0..accounts_per_block {
+        let owner = rng.gen_range(0, num_owners);
+        let balance = rng.gen_range(1, MAX_AMOUNT_PER_OUTPUT);
+        let key = SecretKey::new(&secp_inst, rng);
+        ledger.create_account(rng, owner, key, balance);
+      }
+    }
+    ledger
+  }
+
+  pub fn height(&self) -> u64 {
+    self.height
+  }
+
+  pub fn g_basepoint(&self) -> PublicKey {
+    self.g_basepoint
+  }
+
+  // Moves to the next block; accounts created from now on get the new height
+  pub fn advance(&mut self) -> u64 {
+    self.height += 1;
+    self.height
+  }
+
+  // Adds an account owned by owner at the current height and returns its id
+  pub fn create_account<R: Rng>(&mut self, rng: &mut R, owner: usize, key: SecretKey, balance: u64) -> usize {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let (pubkey, commitment) = create_account(&secp_inst, rng, &key, balance);
+    let id = self.accounts.len();
+
+    self.accounts.push(LedgerAccount { id, pubkey, commitment, created: self.height });
+    self.spent.push(None);
+    self.owners.push(owner);
+    self.keys.push(key);
+    self.balances.push(balance);
+    id
+  }
+
+  // Marks an account as spent at the current height
+  pub fn spend(&mut self, id: usize) {
+    assert!(self.spent[id].is_none());
+    self.spent[id] = Some(self.height);
+  }
+
+  // Spends account id and replaces it at the current height with an update
+  // of it: same owner and secret key, re-randomised public key and commitment,
+  // and delta added to the balance. Returns the new id and the update with its proofs.
+  pub fn update_account(&mut self, id: usize, delta: i64) -> (usize, AccountUpdate) {
+    let balance = self.balances[id] as i64 + delta;
+    assert!(balance >= 0);
+
+    let old = self.accounts[id];
+    let update = AccountUpdate::create(old.pubkey, old.commitment, self.g_basepoint, delta);
+    self.spend(id);
+
+    let new_id = self.accounts.len();
+    self.accounts.push(LedgerAccount {
+      id: new_id,
+      pubkey: update.new_pubkey,
+      commitment: update.new_commitment,
+      created: self.height,
+    });
+    self.spent.push(None);
+    self.owners.push(self.owners[id]);
+    self.keys.push(self.keys[id].clone());
+    self.balances.push(balance as u64);
+
+    (new_id, update)
+  }
+
+  // Moves amount from one account to another by updating both
+  pub fn transfer(&mut self, from: usize, to: usize, amount: u64) -> ((usize, AccountUpdate), (usize, AccountUpdate)) {
+    assert!(from != to);
+
+    let sent = self.update_account(from, -(amount as i64));
+    let received = self.update_account(to, amount as i64);
+    (sent, received)
+  }
+
+  // Adds num_blocks blocks, each with transfers_per_block transfers of a
+  // random part of a random live account's balance to another live account
+  pub fn simulate_transfers<R: Rng>(&mut self, rng: &mut R, num_blocks: u64, transfers_per_block: usize) {
+    for _block in 0..num_blocks {
+      self.advance();
+      for _i in 0..transfers_per_block {
+        let live: Vec<usize> = (0..self.accounts.len())
+          .filter(|&id| self.spent[id].is_none() && self.accounts[id].created < self.height)
+          .collect();
+        if live.len() < 2 {
+          return;
+        }
+
+        let from = live[rng.gen_range(0, live.len())];
+        let to = live[rng.gen_range(0, live.len())];
+        if from == to || self.balances[from] < 2 {
+          continue;
+        }
+        let amount = rng.gen_range(1, self.balances[from]);
+        self.transfer(from, to, amount);
+      }
+    }
+  }
+
+  pub fn num_accounts(&self) -> usize {
+    self.accounts.len()
+  }
+
+  pub fn account(&self, id: usize) -> LedgerAccount {
+    self.accounts[id]
+  }
+
+  pub fn owner(&self, id: usize) -> usize {
+    self.owners[id]
+  }
+
+  pub fn key(&self, id: usize) -> SecretKey {
+    self.keys[id].clone()
+  }
+
+  pub fn balance(&self, id: usize) -> u64 {
+    self.balances[id]
+  }
+
+  // Whether the account exists and is unspent at the given height
+  pub fn is_live(&self, id: usize, height: u64) -> bool {
+    self.accounts[id].created <= height && self.spent[id].is_none_or(|spent| spent > height)
+  }
+
+  // The accounts alive at the given height, in ledger order
+  pub fn snapshot(&self, height: u64) -> LedgerSnapshot {
+    assert!(height <= self.height);
+
+    LedgerSnapshot {
+      height,
+      accounts: (0..self.accounts.len())
+        .filter(|&id| self.is_live(id, height))
+        .map(|id| self.accounts[id])
+        .collect(),
+    }
+  }
+
+  // Ids of the accounts of owner alive at the given height
+  pub fn owned_accounts(&self, owner: usize, height: u64) -> Vec<usize> {
+    (0..self.accounts.len())
+      .filter(|&id| self.owners[id] == owner && self.is_live(id, height))
+      .collect()
+  }
+
+  // Sum of the balances of all accounts alive at the given height
+  pub fn total_supply(&self, height: u64) -> u64 {
+    (0..self.accounts.len())
+      .filter(|&id| self.is_live(id, height))
+      .map(|id| self.balances[id])
+      .sum()
+  }
+}
@@ -2,5 +2,21 @@ pub mod simple_exchange;
 pub mod nummatus_exchange;
 pub mod simple_nizk;
 pub mod nummatus_nizk;
+pub mod update_nizk;
+pub mod nummatus_stream;
+pub mod nummatus_compact;
+
+pub mod ledger;
+pub mod dlog;
+pub mod anonymity;
+pub mod merkle;
+pub mod scan;
+pub mod keychain;
+pub mod audit;
+pub mod adversary;
+pub mod interactive;
+pub mod leakage;
+
+pub mod sweep;
 
 pub mod misc;
\ No newline at end of file
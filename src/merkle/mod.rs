@@ -0,0 +1,239 @@
+use std::collections::{HashMap, HashSet};
+
+use digest::Digest;
+use sha2::Sha256;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+
+use crate::misc::QPublicKey;
+use crate::misc::Nonce;
+use crate::misc::serialize_point;
+use crate::misc::is_valid_point;
+use crate::ledger::LedgerSnapshot;
+use crate::nummatus_exchange::{Nummatus, LedgerVerifyError};
+
+pub type Hash = [u8; 32];
+
+// Leaves, inner nodes and the root are hashed with different prefixes so
+// that an inner node can never be passed off as a leaf or as a root
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+const ROOT_PREFIX: u8 = 0x02;
+
+//hashes a serialized (pubkey, commitment) ledger entry
+pub fn leaf_hash(secp_inst: &Secp256k1, pubkey: &QPublicKey, commitment: &QPublicKey) -> Hash {
+  let mut hasher = Sha256::new();
+  hasher.input([LEAF_PREFIX]);
+  hasher.input(serialize_point(secp_inst, &pubkey.x, true));
+  hasher.input(serialize_point(secp_inst, &pubkey.y, true));
+  hasher.input(serialize_point(secp_inst, &commitment.x, true));
+  hasher.input(serialize_point(secp_inst, &commitment.y, true));
+
+  let mut hash = [0u8; 32];
+  hash.copy_from_slice(&hasher.result());
+  hash
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+  let mut hasher = Sha256::new();
+  hasher.input([NODE_PREFIX]);
+  hasher.input(left);
+  hasher.input(right);
+
+  let mut hash = [0u8; 32];
+  hash.copy_from_slice(&hasher.result());
+  hash
+}
+
+//binds the ledger height into the root, so a root is only valid for one height
+fn root_hash(height: u64, top: &Hash) -> Hash {
+  let mut hasher = Sha256::new();
+  hasher.input([ROOT_PREFIX]);
+  hasher.input(height.to_be_bytes());
+  hasher.input(top);
+
+  let mut hash = [0u8; 32];
+  hash.copy_from_slice(&hasher.result());
+  hash
+}
+
+// Merkle tree over the accounts of a ledger snapshot, in ledger order. A node
+// without a sibling is carried up to the next level unchanged rather than
+// paired with itself, so no two different leaf lists share a root. The root
+// is the top node hashed together with the height.
+pub struct MerkleTree {
+  height: u64,                                //ledger height the tree commits to
+  levels: Vec<Vec<Hash>>,                     //levels[0] are the leaves, the last level is the root
+}
+
+// Proof that a leaf is at position index in a tree with num_leaves leaves
+#[derive (Clone)]
+pub struct MembershipProof {
+  pub index: usize,
+  pub num_leaves: usize,
+  pub siblings: Vec<Hash>,                    //sibling hashes from the leaf up, skipping levels where there is none
+}
+
+impl MerkleTree {
+  pub fn new(height: u64, leaves: Vec<Hash>) -> MerkleTree {
+    assert!(!leaves.is_empty());
+
+    let mut levels = vec![leaves];
+    while levels[levels.len() - 1].len() > 1 {
+      let next = levels[levels.len() - 1]
+        .chunks(2)
+        .map(|pair| if pair.len() == 2 { node_hash(&pair[0], &pair[1]) } else { pair[0] })
+        .collect();
+      levels.push(next);
+    }
+
+    MerkleTree { height, levels }
+  }
+
+  pub fn from_snapshot(snapshot: &LedgerSnapshot) -> MerkleTree {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let leaves = snapshot.accounts.iter()
+      .map(|account| leaf_hash(&secp_inst, &account.pubkey, &account.commitment))
+      .collect();
+
+    MerkleTree::new(snapshot.height, leaves)
+  }
+
+  pub fn height(&self) -> u64 {
+    self.height
+  }
+
+  pub fn root(&self) -> Hash {
+    root_hash(self.height, &self.levels[self.levels.len() - 1][0])
+  }
+
+  pub fn num_leaves(&self) -> usize {
+    self.levels[0].len()
+  }
+
+  pub fn leaves(&self) -> &[Hash] {
+    &self.levels[0]
+  }
+
+  pub fn prove(&self, index: usize) -> MembershipProof {
+    assert!(index < self.num_leaves());
+
+    let mut siblings = Vec::new();
+    let mut position = index;
+    for level in &self.levels[..self.levels.len() - 1] {
+      let sibling = position ^ 1;
+      if sibling < level.len() {
+        siblings.push(level[sibling]);
+      }
+      position /= 2;
+    }
+
+    MembershipProof { index, num_leaves: self.num_leaves(), siblings }
+  }
+}
+
+impl MembershipProof {
+  // Recomputes the root at height from the leaf and compares it with root
+  pub fn verify(&self, height: u64, root: &Hash, leaf: &Hash) -> bool {
+    if self.index >= self.num_leaves {
+      return false;
+    }
+
+    let mut current = *leaf;
+    let mut position = self.index;
+    let mut width = self.num_leaves;
+    let mut siblings = self.siblings.iter();
+
+    while width > 1 {
+      if position ^ 1 < width {
+        let sibling = match siblings.next() {
+          Some(sibling) => sibling,
+          None => return false,
+        };
+        current = if position.is_multiple_of(2) { node_hash(&current, sibling) } else { node_hash(sibling, &current) };
+      }
+      position /= 2;
+      width = width.div_ceil(2);
+    }
+
+    siblings.next().is_none() && root_hash(height, &current) == *root
+  }
+}
+
+// A Nummatus proof together with the Merkle root of the ledger at the audited
+// height and a membership proof for every anonymity-set entry, so that an
+// auditor holding only the root can check the set without the ledger
+pub struct AnchoredNummatus {
+  pub proof: Nummatus,
+  pub height: u64,
+  pub ledger_root: Hash,
+  pub membership_list: Vec<MembershipProof>,
+}
+
+impl AnchoredNummatus {
+  // Proves membership of every entry of proof in the tree, or returns the
+  // positions of the entries that are not leaves of it
+  pub fn new(proof: Nummatus, tree: &MerkleTree) -> Result<AnchoredNummatus, LedgerVerifyError> {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let positions: HashMap<Hash, usize> = tree.leaves().iter().enumerate().map(|(i, leaf)| (*leaf, i)).collect();
+
+    let mut membership_list = Vec::with_capacity(proof.pubkey_list.len());
+    let mut unknown = Vec::new();
+    for i in 0..proof.pubkey_list.len() {
+      let leaf = leaf_hash(&secp_inst, &proof.pubkey_list[i], &proof.commitment_list[i]);
+      match positions.get(&leaf) {
+        Some(&index) => membership_list.push(tree.prove(index)),
+        None => unknown.push(i),
+      }
+    }
+
+    if !unknown.is_empty() {
+      return Err(LedgerVerifyError::UnknownEntries { height: tree.height(), indices: unknown });
+    }
+    Ok(AnchoredNummatus {
+      proof,
+      height: tree.height(),
+      ledger_root: tree.root(),
+      membership_list,
+    })
+  }
+
+  // Checks every entry's membership proof against the published root and
+  // every NummatusPoK against the auditor's nonce. Entries must be distinct
+  // leaves of the same tree so that no account is counted twice.
+  pub fn verify(&self, nonce: Option<&Nonce>) -> bool {
+
+    // An empty proof, or one whose lists do not line up, never verifies
+    let len = self.membership_list.len();
+    if len == 0 || self.proof.pubkey_list.len() != len || self.proof.commitment_list.len() != len {
+      return false;
+    }
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let num_leaves = self.membership_list[0].num_leaves;
+    let mut seen = HashSet::new();
+
+    for (i, membership) in self.membership_list.iter().enumerate() {
+      if membership.num_leaves != num_leaves || !seen.insert(membership.index) {
+        return false;
+      }
+      // Placeholder or off-curve points never verify, and cannot be hashed into a leaf
+      let (pubkey, commitment) = (&self.proof.pubkey_list[i], &self.proof.commitment_list[i]);
+      if ![pubkey.x, pubkey.y, commitment.x, commitment.y].iter().all(|point| is_valid_point(&secp_inst, point)) {
+        return false;
+      }
+      let leaf = leaf_hash(&secp_inst, pubkey, commitment);
+      if !membership.verify(self.height, &self.ledger_root, &leaf) {
+        return false;
+      }
+    }
+
+    self.proof.verify_with_nonce(nonce)
+  }
+
+  // As verify, but also requires the published root to be the one the
+  // auditor trusts for this height, e.g. taken from a block header
+  pub fn verify_with_root(&self, height: u64, root: &Hash, nonce: Option<&Nonce>) -> bool {
+    self.height == height && self.ledger_root == *root && self.verify(nonce)
+  }
+}
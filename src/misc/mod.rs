@@ -1,12 +1,56 @@
+use std::io::{self, Read, Write};
+use std::mem;
+use std::str::FromStr;
+
 use digest::Digest;
-use sha2::Sha256;
+use rand::Rng;
+use sha2::Sha512;
 
 use secp256k1zkp as secp;
 use secp::Secp256k1;
+use secp::constants::CURVE_ORDER;
 use secp::key::{SecretKey, PublicKey};
 
 pub const MAX_AMOUNT_PER_OUTPUT: u64 = 1000;
 
+/// Size of a compressed secp256k1 point on the wire
+pub const POINT_SIZE: usize = 33;
+
+/// Size of a scalar on the wire
+pub const SCALAR_SIZE: usize = 32;
+
+/// Size of an auditor nonce
+pub const NONCE_SIZE: usize = 32;
+
+// Fresh value chosen by the auditor for each audit and absorbed into every
+// challenge, so that a proof made for one audit does not verify in another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nonce(pub [u8; NONCE_SIZE]);
+
+impl Nonce {
+    pub fn random<R: Rng>(rng: &mut R) -> Nonce {
+        let mut nonce = [0u8; NONCE_SIZE];
+        rng.fill(&mut nonce);
+        Nonce(nonce)
+    }
+}
+
+// Parses 64 hex digits, as given on the command line
+impl FromStr for Nonce {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Nonce, String> {
+        if s.len() != 2 * NONCE_SIZE || !s.is_ascii() {
+            return Err(format!("expected {} hex digits, got {:?}", 2 * NONCE_SIZE, s));
+        }
+        let mut nonce = [0u8; NONCE_SIZE];
+        for (i, byte) in nonce.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).map_err(|e| format!("invalid nonce {:?}: {}", s, e))?;
+        }
+        Ok(Nonce(nonce))
+    }
+}
+
 /// The number curve_order-1 encoded as a secret key
 pub const MINUS_ONE_KEY: SecretKey = SecretKey([
     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
@@ -47,12 +91,125 @@ pub struct QPublicKey {
 }
 
 impl QPublicKey {
+    // A zeroed placeholder to be filled in, which no verifier accepts, so
+    // deliberately not a Default
+    #[allow(clippy::new_without_default)]
     pub fn new() -> QPublicKey {
         QPublicKey {
             x : PublicKey::new(),
             y : PublicKey::new(),
         }
     }
+
+    //re-randomises a Quisquis public key (x, y) to (x^r, y^r); the secret key k with y = x^k is unchanged
+    pub fn rerandomise(&self, secp_inst: &Secp256k1, r: &SecretKey) -> QPublicKey {
+        QPublicKey {
+            x : single_base_product(secp_inst, self.x, r.clone()),
+            y : single_base_product(secp_inst, self.y, r.clone()),
+        }
+    }
+
+    //updates a commitment (c, d) under public key (a, b) to (c * a^t, d * g^delta * b^t),
+    //adding delta to the committed amount and re-randomising it with t
+    pub fn update_commitment(
+        &self,
+        secp_inst: &Secp256k1,
+        pubkey: &QPublicKey,
+        g: PublicKey,
+        delta: i64,
+        t: &SecretKey,
+        ) -> QPublicKey {
+
+        let x = PublicKey::from_combination(secp_inst, vec![&self.x, &single_base_product(secp_inst, pubkey.x, t.clone())]).unwrap();
+        let mut y_terms = vec![self.y, single_base_product(secp_inst, pubkey.y, t.clone())];
+        if delta != 0 {
+            y_terms.push(single_base_product(secp_inst, g, signed_amount_to_key(secp_inst, delta)));
+        }
+        let y = PublicKey::from_combination(secp_inst, y_terms.iter().collect()).unwrap();
+
+        QPublicKey { x, y }
+    }
+
+    //writes x and y as two compressed points
+    pub fn write_to<W: Write>(&self, secp_inst: &Secp256k1, writer: &mut W) -> io::Result<()> {
+        write_point(secp_inst, writer, &self.x)?;
+        write_point(secp_inst, writer, &self.y)
+    }
+
+    //reads x and y as two compressed points
+    pub fn read_from<R: Read>(secp_inst: &Secp256k1, reader: &mut R) -> io::Result<QPublicKey> {
+        let x = read_point(secp_inst, reader)?;
+        let y = read_point(secp_inst, reader)?;
+
+        Ok(QPublicKey { x, y })
+    }
+}
+
+// Encodes a point, compressed or not. The ArrayVec from serialize_vec is
+// copied out and forgotten instead of dropped: arrayvec 0.3 empties itself on
+// drop through an out-of-bounds get_unchecked_mut, which aborts debug builds.
+pub fn serialize_point(secp_inst: &Secp256k1, point: &PublicKey, compressed: bool) -> Vec<u8> {
+    let encoding = point.serialize_vec(secp_inst, compressed);
+    let bytes = encoding.to_vec();
+    mem::forget(encoding);
+    bytes
+}
+
+//writes a point in compressed form
+pub fn write_point<W: Write>(secp_inst: &Secp256k1, writer: &mut W, point: &PublicKey) -> io::Result<()> {
+    writer.write_all(&serialize_point(secp_inst, point, true))
+}
+
+//reads a compressed point, rejecting encodings that are not on the curve
+pub fn read_point<R: Read>(secp_inst: &Secp256k1, reader: &mut R) -> io::Result<PublicKey> {
+    let mut buf = [0u8; POINT_SIZE];
+    reader.read_exact(&mut buf)?;
+
+    PublicKey::from_slice(secp_inst, &buf)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid curve point"))
+}
+
+//fills buf with the next fixed-size record, returning false if the reader
+//ends cleanly before it and an error if it ends partway through
+pub fn read_record_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated record")),
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(true)
+}
+
+//writes a scalar as 32 big-endian bytes
+pub fn write_scalar<W: Write>(writer: &mut W, scalar: &SecretKey) -> io::Result<()> {
+    writer.write_all(&scalar[..])
+}
+
+//reads a scalar, rejecting zero and values at or above the curve order
+pub fn read_scalar<R: Read>(secp_inst: &Secp256k1, reader: &mut R) -> io::Result<SecretKey> {
+    let mut buf = [0u8; SCALAR_SIZE];
+    reader.read_exact(&mut buf)?;
+
+    SecretKey::from_slice(secp_inst, &buf)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid scalar"))
+}
+
+//whether point is a valid curve point, rejecting the zeroed placeholder of
+//PublicKey::new() and raw values that do not round-trip through an encoding
+pub fn is_valid_point(secp_inst: &Secp256k1, point: &PublicKey) -> bool {
+    point.is_valid() && PublicKey::from_slice(secp_inst, &serialize_point(secp_inst, point, false)) == Ok(*point)
+}
+
+//whether scalar is in [1, n), rejecting the ZERO_KEY placeholder
+pub fn is_valid_scalar(secp_inst: &Secp256k1, scalar: &SecretKey) -> bool {
+    SecretKey::from_slice(secp_inst, &scalar[..]).is_ok()
 }
 
 pub fn amount_to_key (secp_inst: &Secp256k1, amount: u64) -> SecretKey {
@@ -61,11 +218,63 @@ pub fn amount_to_key (secp_inst: &Secp256k1, amount: u64) -> SecretKey {
     let amount_as_bytes = amount.to_be_bytes();
     let mut amount_scalar_vec = vec![0u8; 24];
     amount_scalar_vec.extend_from_slice(&amount_as_bytes);
-    let amount_scalar = SecretKey::from_slice(&secp_inst, amount_scalar_vec.as_slice()).unwrap();
+    let amount_scalar = SecretKey::from_slice(secp_inst, amount_scalar_vec.as_slice()).unwrap();
+
+    amount_scalar
+}
+
+//converts a non-zero signed amount to a scalar, mapping -v to curve_order - v
+pub fn signed_amount_to_key (secp_inst: &Secp256k1, amount: i64) -> SecretKey {
+    let mut amount_scalar = amount_to_key(secp_inst, amount.unsigned_abs());
+    if amount < 0 {
+        amount_scalar.mul_assign(secp_inst, &MINUS_ONE_KEY).unwrap();
+    }
 
     amount_scalar
 }
 
+//takes amount v and blinding factor k as arguments and returns the Pedersen commitment g^v * h^k,
+//which is h^k for v = 0 since g^0 is the identity
+pub fn pedersen_commitment (
+    secp_inst: &Secp256k1,
+    g: PublicKey,
+    h: PublicKey,
+    amount: u64,
+    blinding: SecretKey,
+    ) -> PublicKey {
+
+    if amount == 0 {
+        return single_base_product(secp_inst, h, blinding);
+    }
+    double_base_product(secp_inst, g, h, amount_to_key(secp_inst, amount), blinding)
+}
+
+//Pedersen commitment g^v * h^k computed with the same operations whatever v
+//is, including v = 0 where pedersen_commitment falls back to h^k: for a
+//fresh random t it is g^(v + t) * h^k * g^-t. Owned entries (v, k) and decoys
+//(0, r) then only differ in the scalars, which callers pick with select_scalar.
+pub fn uniform_pedersen_commitment<R: Rng> (
+    secp_inst: &Secp256k1,
+    rng: &mut R,
+    g: PublicKey,
+    h: PublicKey,
+    amount: u64,
+    blinding: SecretKey,
+    ) -> PublicKey {
+
+    let mut amount_scalar = [0u8; SCALAR_SIZE];
+    amount_scalar[SCALAR_SIZE - 8..].copy_from_slice(&amount.to_be_bytes());
+    let t = SecretKey::new(secp_inst, rng);
+    let mut shifted = t.clone();
+    shifted.add_assign(secp_inst, &SecretKey(amount_scalar)).unwrap();      // v + t
+    let mut minus_t = t;
+    minus_t.mul_assign(secp_inst, &MINUS_ONE_KEY).unwrap();                  // -t
+
+    let shifted_com = double_base_product(secp_inst, g, h, shifted, blinding);
+    let unshift = single_base_product(secp_inst, g, minus_t);
+    PublicKey::from_combination(secp_inst, vec![&shifted_com, &unshift]).unwrap()
+}
+
 //takes base and exp as arguments and returns base^exp
 pub fn single_base_product (
     secp_inst: &Secp256k1, 
@@ -73,8 +282,8 @@ pub fn single_base_product (
     exp: SecretKey, 
     ) -> PublicKey {
 
-    let mut exp_base = base.clone();
-    exp_base.mul_assign(&secp_inst, &exp).unwrap();
+    let mut exp_base = base;
+    exp_base.mul_assign(secp_inst, &exp).unwrap();
     
     exp_base
 }
@@ -88,12 +297,24 @@ pub fn double_base_product (
     exp_2: SecretKey,
     ) -> PublicKey {
 
-    let mut exp1_base1 = base_1.clone();
-    exp1_base1.mul_assign(&secp_inst, &exp_1).unwrap();
-    let mut exp2_base2 = base_2.clone();
-    exp2_base2.mul_assign(&secp_inst, &exp_2).unwrap();
+    checked_double_base_product(secp_inst, base_1, base_2, exp_1, exp_2).unwrap()
+}
+
+//as double_base_product, but returns None instead of panicking when the product is the identity
+pub fn checked_double_base_product (
+    secp_inst: &Secp256k1, 
+    base_1: PublicKey, 
+    base_2: PublicKey, 
+    exp_1: SecretKey, 
+    exp_2: SecretKey,
+    ) -> Option<PublicKey> {
+
+    let mut exp1_base1 = base_1;
+    exp1_base1.mul_assign(secp_inst, &exp_1).ok()?;
+    let mut exp2_base2 = base_2;
+    exp2_base2.mul_assign(secp_inst, &exp_2).ok()?;
 
-    PublicKey::from_combination(&secp_inst, vec![&exp1_base1, &exp2_base2]).unwrap()
+    PublicKey::from_combination(secp_inst, vec![&exp1_base1, &exp2_base2]).ok()
 }
 
 //takes base1, base2, base3, exp1, exp2, and exp3 as arguments and returns base1^exp1 * base2^exp2 * base3^exp3
@@ -107,14 +328,28 @@ pub fn triple_base_product (
     exp_3: SecretKey,
     ) -> PublicKey {
 
-    let mut exp1_base1 = base_1.clone();
-    exp1_base1.mul_assign(&secp_inst, &exp_1).unwrap();
-    let mut exp2_base2 = base_2.clone();
-    exp2_base2.mul_assign(&secp_inst, &exp_2).unwrap();
-    let mut exp3_base3 = base_3.clone();
-    exp3_base3.mul_assign(&secp_inst, &exp_3).unwrap();
+    checked_triple_base_product(secp_inst, base_1, base_2, base_3, exp_1, exp_2, exp_3).unwrap()
+}
 
-    PublicKey::from_combination(&secp_inst, vec![&exp1_base1, &exp2_base2, &exp3_base3]).unwrap()
+//as triple_base_product, but returns None instead of panicking when the product is the identity
+pub fn checked_triple_base_product (
+    secp_inst: &Secp256k1, 
+    base_1: PublicKey, 
+    base_2: PublicKey, 
+    base_3: PublicKey, 
+    exp_1: SecretKey, 
+    exp_2: SecretKey,
+    exp_3: SecretKey,
+    ) -> Option<PublicKey> {
+
+    let mut exp1_base1 = base_1;
+    exp1_base1.mul_assign(secp_inst, &exp_1).ok()?;
+    let mut exp2_base2 = base_2;
+    exp2_base2.mul_assign(secp_inst, &exp_2).ok()?;
+    let mut exp3_base3 = base_3;
+    exp3_base3.mul_assign(secp_inst, &exp_3).ok()?;
+
+    PublicKey::from_combination(secp_inst, vec![&exp1_base1, &exp2_base2, &exp3_base3]).ok()
 }
 
 //takes num and den as arguments and returns num * den^-1
@@ -124,59 +359,155 @@ pub fn ratio (
     den: PublicKey,
     ) -> PublicKey {
 
-    let mut minus_den = den.clone();
-    minus_den.mul_assign(&secp_inst, &MINUS_ONE_KEY).unwrap();
+    checked_ratio(secp_inst, num, den).unwrap()
+}
 
-    PublicKey::from_combination(&secp_inst, vec![&num, &minus_den]).unwrap()
+//as ratio, but returns None instead of panicking when num == den and the result is the identity
+pub fn checked_ratio (
+    secp_inst: &Secp256k1, 
+    num: PublicKey, 
+    den: PublicKey,
+    ) -> Option<PublicKey> {
+
+    let mut minus_den = den;
+    minus_den.mul_assign(secp_inst, &MINUS_ONE_KEY).ok()?;
+
+    PublicKey::from_combination(secp_inst, vec![&num, &minus_den]).ok()
 }
 
+// Domain tags of the per-output Fiat-Shamir challenges, so that no hash
+// computed for one kind of proof is a challenge of another
+const SIMPLE_CHALLENGE_DOMAIN: &[u8] = b"quisquis/simplus/challenge";
+const NUMMATUS_CHALLENGE_DOMAIN: &[u8] = b"quisquis/nummatus/challenge";
+
+// 2^256 mod n, where n is the curve order
+const TWO_POW_256_MOD_N: SecretKey = SecretKey([
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x45, 0x51, 0x23, 0x19, 0x50, 0xb7, 0x5f, 0xc4,
+    0x40, 0x2d, 0xa1, 0x73, 0x2f, 0xc9, 0xbe, 0xbf
+]);
+
 //computes hash of arguments for Simplus signature and returns a scalar
+#[allow(clippy::too_many_arguments)]
 pub fn hash_simple_tx (
     secp_inst: &Secp256k1,
     a1: PublicKey, a2: PublicKey, a3: PublicKey, a4: PublicKey, a5: PublicKey,
     a6: PublicKey, a7: PublicKey, a8: PublicKey,
+    nonce: Option<&Nonce>,
     ) -> SecretKey {
 
-    let mut hasher = Sha256::new();
-    hasher.input(a1.serialize_vec(&secp_inst, true));
-    hasher.input(a2.serialize_vec(&secp_inst, true));
-    hasher.input(a3.serialize_vec(&secp_inst, true));
-    hasher.input(a4.serialize_vec(&secp_inst, true));
-    hasher.input(a5.serialize_vec(&secp_inst, true));
-    hasher.input(a6.serialize_vec(&secp_inst, true));
-    hasher.input(a7.serialize_vec(&secp_inst, true));
-    hasher.input(a8.serialize_vec(&secp_inst, true));                  
-    
-    SecretKey::from_slice(&secp_inst, &hasher.result()).unwrap()
+    hash_points(secp_inst, SIMPLE_CHALLENGE_DOMAIN, &[a1, a2, a3, a4, a5, a6, a7, a8], nonce)
 }
 
 //computes hash of arguments for Nummatus signature and returns a scalar
+#[allow(clippy::too_many_arguments)]
 pub fn hash_special_tx (
     secp_inst: &Secp256k1,
     a1: PublicKey, a2: PublicKey, a3: PublicKey, a4: PublicKey, a5: PublicKey,
-    a6: PublicKey, a7: PublicKey, a8: PublicKey, a9: PublicKey
+    a6: PublicKey, a7: PublicKey, a8: PublicKey, a9: PublicKey,
+    nonce: Option<&Nonce>,
     ) -> SecretKey {
 
-    let mut hasher = Sha256::new();
-    hasher.input(a1.serialize_vec(&secp_inst, true));
-    hasher.input(a2.serialize_vec(&secp_inst, true));
-    hasher.input(a3.serialize_vec(&secp_inst, true));
-    hasher.input(a4.serialize_vec(&secp_inst, true));
-    hasher.input(a5.serialize_vec(&secp_inst, true));
-    hasher.input(a6.serialize_vec(&secp_inst, true));
-    hasher.input(a7.serialize_vec(&secp_inst, true));
-    hasher.input(a8.serialize_vec(&secp_inst, true));
-    hasher.input(a9.serialize_vec(&secp_inst, true));    
-    
-    SecretKey::from_slice(&secp_inst, &hasher.result()).unwrap()
+    hash_points(secp_inst, NUMMATUS_CHALLENGE_DOMAIN, &[a1, a2, a3, a4, a5, a6, a7, a8, a9], nonce)
+}
+
+//computes hash of the nonce, if any, and any number of compressed points under a domain tag
+pub fn hash_points (secp_inst: &Secp256k1, domain: &[u8], points: &[PublicKey], nonce: Option<&Nonce>) -> SecretKey {
+
+    let mut data = Vec::with_capacity(NONCE_SIZE + points.len() * POINT_SIZE);
+    if let Some(nonce) = nonce {
+        data.extend_from_slice(&nonce.0);
+    }
+    for point in points {
+        data.extend_from_slice(&serialize_point(secp_inst, point, true));
+    }
+
+    hash_to_scalar(secp_inst, domain, &data)
+}
+
+// Hashes data to a scalar: SHA-512 of the length-prefixed domain tag, a
+// counter and the data, reduced mod n from 512 bits so that the result is
+// uniform up to a bias of 2^-256. The rare digest that reduces to zero, which
+// no SecretKey can hold, is replaced by a rehash with the next counter.
+pub fn hash_to_scalar (secp_inst: &Secp256k1, domain: &[u8], data: &[u8]) -> SecretKey {
+    assert!(domain.len() <= u8::MAX as usize);
+
+    let mut counter = 0u32;
+    loop {
+        let mut hasher = Sha512::new();
+        hasher.input([domain.len() as u8]);
+        hasher.input(domain);
+        hasher.input(counter.to_be_bytes());
+        hasher.input(data);
+
+        if let Some(scalar) = reduce_wide(secp_inst, &hasher.result()) {
+            return scalar;
+        }
+        counter += 1;
+    }
+}
+
+//reduces a 64-byte big-endian value hi || lo mod n as hi * 2^256 + lo, or None if the result is zero
+pub fn reduce_wide (secp_inst: &Secp256k1, wide: &[u8]) -> Option<SecretKey> {
+    assert!(wide.len() == 2 * SCALAR_SIZE);
+
+    let hi = SecretKey::from_slice(secp_inst, &reduce_256(&wide[..SCALAR_SIZE])).ok();
+    let lo = SecretKey::from_slice(secp_inst, &reduce_256(&wide[SCALAR_SIZE..])).ok();
+
+    let hi = hi.map(|mut hi| {
+        hi.mul_assign(secp_inst, &TWO_POW_256_MOD_N).unwrap();    // nonzero, as n is prime
+        hi
+    });
+    match (hi, lo) {
+        (Some(mut sum), Some(lo)) => sum.add_assign(secp_inst, &lo).ok().map(|_| sum),
+        (hi, lo) => hi.or(lo),
+    }
+}
+
+//reduces a 256-bit big-endian value mod n, which takes at most one subtraction as it is below 2n
+fn reduce_256 (x: &[u8]) -> [u8; SCALAR_SIZE] {
+    let mut reduced = [0u8; SCALAR_SIZE];
+    reduced.copy_from_slice(x);
+
+    if reduced >= CURVE_ORDER {
+        let mut borrow = 0i16;
+        for (byte, order_byte) in reduced.iter_mut().zip(CURVE_ORDER.iter()).rev() {
+            let diff = *byte as i16 - *order_byte as i16 - borrow;
+            borrow = (diff < 0) as i16;
+            *byte = diff as u8;
+        }
+    }
+    reduced
+}
+
+//returns 1 if k is zero and 0 otherwise, reading every byte whatever their values
+pub fn scalar_is_zero (k: &SecretKey) -> u8 {
+    let acc = k.0.iter().fold(0u8, |acc, byte| acc | byte);
+    (((acc as u16).wrapping_sub(1)) >> 8) as u8 & 1
+}
+
+//returns a if choice is 1 and b if choice is 0, without branching on choice
+pub fn select_scalar (choice: u8, a: &SecretKey, b: &SecretKey) -> SecretKey {
+    let mask = 0u8.wrapping_sub(choice);
+    let mut selected = [0u8; SCALAR_SIZE];
+    for (byte, (a_byte, b_byte)) in selected.iter_mut().zip(a.0.iter().zip(b.0.iter())) {
+        *byte = (a_byte & mask) | (b_byte & !mask);
+    }
+    SecretKey(selected)
 }
 
 //takes 3 Secretkeys a,b,x as arguments and returns a-b*x
 pub fn a_minus_bx (secp_inst: &Secp256k1, a: SecretKey, b: SecretKey, x: SecretKey) -> SecretKey {
+    checked_a_minus_bx(secp_inst, a, b, x).unwrap()
+}
+
+//as a_minus_bx, but returns None instead of panicking when an operand or the result is zero
+pub fn checked_a_minus_bx (secp_inst: &Secp256k1, a: SecretKey, b: SecretKey, x: SecretKey) -> Option<SecretKey> {
     let mut result = x;                                        // result = x
-    result.mul_assign(&secp_inst, &MINUS_ONE_KEY).unwrap();    // result = -x
-    result.mul_assign(&secp_inst, &b).unwrap();                // result = -b*x
-    result.add_assign(&secp_inst, &a).unwrap();                // result = a - b*x
+    result.mul_assign(secp_inst, &MINUS_ONE_KEY).ok()?;       // result = -x
+    result.mul_assign(secp_inst, &b).ok()?;                   // result = -b*x
+    result.add_assign(secp_inst, &a).ok()?;                   // result = a - b*x
 
-    result
+    Some(result)
 }
@@ -0,0 +1,236 @@
+use std::io::{self, Read, Write};
+
+use rand::thread_rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey, ONE_KEY};
+
+use crate::misc::QPublicKey;
+use crate::misc::{GENERATOR_G, POINT_SIZE, SCALAR_SIZE};
+use crate::misc::hash_points;
+use crate::misc::Nonce;
+use crate::misc::checked_a_minus_bx;
+use crate::misc::{is_valid_point, is_valid_scalar};
+use crate::misc::{read_point, read_record_or_eof, read_scalar, write_point, write_scalar};
+use crate::nummatus_nizk::{NummatusPoK, first_message, response, recompute_first_message};
+
+// Domain tag of the global challenge, distinct from that of per-output NummatusPoKs
+const CHALLENGE_DOMAIN: &[u8] = b"quisquis/nummatus-compact/challenge";
+
+// Per-output part of a compact Nummatus proof. All outputs share one
+// Fiat-Shamir challenge e, so only the split e1 is stored and e2 = e - e1.
+#[derive (Clone)]
+pub struct CompactNummatusPoK {
+  pub e1 : SecretKey,
+  pub s1 : SecretKey,
+  pub s2 : SecretKey,
+}
+
+/// Size of a serialized CompactNummatusPoK
+pub const COMPACT_NUMMATUS_POK_SIZE: usize = 3 * SCALAR_SIZE;
+
+/// Size of one (pubkey, commitment, pedersen_com, e1, s1, s2) record on the wire
+pub const COMPACT_NUMMATUS_RECORD_SIZE: usize = 5 * POINT_SIZE + COMPACT_NUMMATUS_POK_SIZE;
+
+// Nummatus proof where the first messages of every output are hashed into a
+// single global challenge. This saves one scalar per output compared to the
+// per-output hash_special_tx layout, at the cost of having to see the whole
+// anonymity set before the challenge can be checked.
+pub struct CompactNummatus {
+  pub pubkey_list: Vec<QPublicKey>,           //Publickey for Quisquis
+  pub commitment_list: Vec<QPublicKey>,       //Quisquis commitment
+  pub pedersen_com_list: Vec<PublicKey>,      //Pedersen commitment
+  pub pok_list: Vec<CompactNummatusPoK>,      //per-output challenge splits and responses
+  pub challenge: SecretKey,                   //global challenge e = H(h, all entries, all first messages)
+  g_basepoint: PublicKey,                     //g
+  h_basepoint: PublicKey,                     //h which is computed at height j of Quisquis blockchain
+}
+
+//appends h, then a, b, c, d, p for every output and v1, v2, v3 for every output
+fn challenge_points(
+  h_j: PublicKey,
+  pubkey_list: &[QPublicKey],
+  commitment_list: &[QPublicKey],
+  pedersen_com_list: &[PublicKey],
+  first_messages: &[(PublicKey, PublicKey, PublicKey)],
+  ) -> Vec<PublicKey> {
+
+  let mut points = Vec::with_capacity(1 + 8 * pubkey_list.len());
+  points.push(h_j);
+  for i in 0..pubkey_list.len() {
+    points.push(pubkey_list[i].x);                //a
+    points.push(pubkey_list[i].y);                //b
+    points.push(commitment_list[i].x);            //c
+    points.push(commitment_list[i].y);            //d
+    points.push(pedersen_com_list[i]);            //p
+  }
+  for &(v1, v2, v3) in first_messages {
+    points.push(v1);
+    points.push(v2);
+    points.push(v3);
+  }
+  points
+}
+
+impl CompactNummatus {
+  // Proves the whole anonymity set. own_keys[i] is the secret key of an owned
+  // output, or ZERO_KEY for a decoy whose Pedersen blinding factor is decoy_keys[i].
+  #[allow(clippy::too_many_arguments)]
+  pub fn create(
+    g_j: PublicKey,
+    h_j: PublicKey,
+    pubkey_list: Vec<QPublicKey>,
+    commitment_list: Vec<QPublicKey>,
+    pedersen_com_list: Vec<PublicKey>,
+    own_keys: &[SecretKey],
+    decoy_keys: &[SecretKey],
+    nonce: Option<&Nonce>,
+    ) -> CompactNummatus {
+
+    assert!(pubkey_list.len() == commitment_list.len());
+    assert!(pubkey_list.len() == pedersen_com_list.len());
+    assert!(pubkey_list.len() == own_keys.len());
+    assert!(pubkey_list.len() == decoy_keys.len());
+
+    let mut rng = thread_rng();
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+    let mut states = Vec::with_capacity(pubkey_list.len());
+    let mut first_messages = Vec::with_capacity(pubkey_list.len());
+
+    for i in 0..pubkey_list.len() {
+      let (first_message, state) = first_message(&secp_inst, &mut rng, pubkey_list[i], commitment_list[i],
+                                     pedersen_com_list[i], h_j, &own_keys[i], &decoy_keys[i]);
+      first_messages.push(first_message);
+      states.push(state);
+    }
+
+    let challenge = hash_points(&secp_inst, CHALLENGE_DOMAIN, &challenge_points(h_j, &pubkey_list, &commitment_list, &pedersen_com_list, &first_messages), nonce);
+
+    let pok_list = states.into_iter().map(|state| {
+      let rpok = response(&secp_inst, state, &challenge);
+      CompactNummatusPoK { e1: rpok.e1, s1: rpok.s1, s2: rpok.s2 }
+    }).collect();
+
+    CompactNummatus {
+      pubkey_list,
+      commitment_list,
+      pedersen_com_list,
+      pok_list,
+      challenge,
+      g_basepoint: g_j,
+      h_basepoint: h_j,
+    }
+  }
+
+  pub fn verify(&self) -> bool {
+    self.verify_with_nonce(None)
+  }
+
+  // Verifies a proof made for the audit with this nonce
+  pub fn verify_with_nonce(&self, nonce: Option<&Nonce>) -> bool {
+
+    let len = self.pubkey_list.len();
+    if len == 0 || self.commitment_list.len() != len || self.pedersen_com_list.len() != len || self.pok_list.len() != len {
+      return false;
+    }
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let h_j = self.h_basepoint;
+
+    // Placeholder or off-curve points and out-of-range scalars never verify
+    if !is_valid_point(&secp_inst, &h_j) || !is_valid_scalar(&secp_inst, &self.challenge) {
+      return false;
+    }
+    let mut first_messages = Vec::with_capacity(len);
+
+    for i in 0..len {
+      let points = [self.pubkey_list[i].x, self.pubkey_list[i].y, self.commitment_list[i].x, self.commitment_list[i].y, self.pedersen_com_list[i]];
+      let scalars = [&self.pok_list[i].e1, &self.pok_list[i].s1, &self.pok_list[i].s2];
+      if !points.iter().all(|point| is_valid_point(&secp_inst, point)) || !scalars.iter().all(|scalar| is_valid_scalar(&secp_inst, scalar)) {
+        return false;
+      }
+
+      // e_2 = e - e_1, which is zero for e_1 = e and is then never a valid split
+      let e2 = match checked_a_minus_bx(&secp_inst, self.challenge.clone(), ONE_KEY, self.pok_list[i].e1.clone()) {
+        Some(e2) => e2,
+        None => return false,
+      };
+      let rpok = NummatusPoK {
+        e1: self.pok_list[i].e1.clone(),
+        e2,
+        s1: self.pok_list[i].s1.clone(),
+        s2: self.pok_list[i].s2.clone(),
+      };
+      match recompute_first_message(&secp_inst, self.pubkey_list[i], self.commitment_list[i], self.pedersen_com_list[i], h_j, &rpok) {
+        Some(v) => first_messages.push(v),
+        None => return false,
+      }
+    }
+
+    let hash_scalar = hash_points(&secp_inst, CHALLENGE_DOMAIN, &challenge_points(h_j, &self.pubkey_list, &self.commitment_list, &self.pedersen_com_list, &first_messages), nonce);
+
+    self.challenge == hash_scalar    // comparing the global challenge and evaluation of the scalar-hash
+  }
+
+  pub fn g_basepoint(&self) -> PublicKey {
+    self.g_basepoint
+  }
+
+  pub fn h_basepoint(&self) -> PublicKey {
+    self.h_basepoint
+  }
+
+  // Writes h, the global challenge, then (pubkey, commitment, pedersen_com, e1, s1, s2) per output
+  pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+    write_point(&secp_inst, writer, &self.h_basepoint)?;
+    write_scalar(writer, &self.challenge)?;
+    for i in 0..self.pubkey_list.len() {
+      self.pubkey_list[i].write_to(&secp_inst, writer)?;
+      self.commitment_list[i].write_to(&secp_inst, writer)?;
+      write_point(&secp_inst, writer, &self.pedersen_com_list[i])?;
+      write_scalar(writer, &self.pok_list[i].e1)?;
+      write_scalar(writer, &self.pok_list[i].s1)?;
+      write_scalar(writer, &self.pok_list[i].s2)?;
+    }
+    Ok(())
+  }
+
+  // Reads a proof written by write_to, one fixed-size record at a time until
+  // the reader ends. Points and scalars are validated as they are decoded.
+  pub fn read_from<R: Read>(reader: &mut R) -> io::Result<CompactNummatus> {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+    let h_basepoint = read_point(&secp_inst, reader)?;
+    let challenge = read_scalar(&secp_inst, reader)?;
+    let mut proof = CompactNummatus {
+      pubkey_list: Vec::new(),
+      commitment_list: Vec::new(),
+      pedersen_com_list: Vec::new(),
+      pok_list: Vec::new(),
+      challenge,
+      g_basepoint: PublicKey::from_slice(&secp_inst, &GENERATOR_G).unwrap(),
+      h_basepoint,
+    };
+
+    let mut buf = [0u8; COMPACT_NUMMATUS_RECORD_SIZE];
+    while read_record_or_eof(reader, &mut buf)? {
+      let mut bytes = &buf[..];
+      proof.pubkey_list.push(QPublicKey::read_from(&secp_inst, &mut bytes)?);
+      proof.commitment_list.push(QPublicKey::read_from(&secp_inst, &mut bytes)?);
+      proof.pedersen_com_list.push(read_point(&secp_inst, &mut bytes)?);
+      proof.pok_list.push(CompactNummatusPoK {
+        e1: read_scalar(&secp_inst, &mut bytes)?,
+        s1: read_scalar(&secp_inst, &mut bytes)?,
+        s2: read_scalar(&secp_inst, &mut bytes)?,
+      });
+    }
+
+    if proof.pok_list.is_empty() {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "compact Nummatus proof without records"));
+    }
+    Ok(proof)
+  }
+}
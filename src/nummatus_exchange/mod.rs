@@ -1,3 +1,9 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
 use rand::{thread_rng, Rng};
 use rand::seq::SliceRandom;
 use secp256k1zkp as secp;
@@ -9,8 +15,21 @@ use crate::misc::GENERATOR_G;
 use crate::misc::GENERATOR_H;
 use crate::misc::MAX_AMOUNT_PER_OUTPUT;
 use crate::misc::amount_to_key;
+use crate::misc::{read_point, write_point};
+use crate::misc::uniform_pedersen_commitment;
+use crate::misc::{scalar_is_zero, select_scalar};
+use crate::misc::Nonce;
 
 use crate::nummatus_nizk::NummatusPoK;
+use crate::nummatus_stream::{read_record, write_record};
+use crate::nummatus_compact::CompactNummatus;
+use crate::ledger::{Ledger, LedgerSnapshot};
+use crate::dlog::{DiscreteLogTable, decrypt_commitment};
+use crate::anonymity::{AnonymitySetSelector, WholeLedger};
+use crate::merkle::{AnchoredNummatus, MerkleTree};
+use crate::scan::{ScanMatch, scan_parallel};
+use crate::keychain::Keychain;
+use crate::interactive::NummatusProver;
 
 pub struct Nummatus {
   pub pubkey_list: Vec<QPublicKey>,           //Publickey for Quisquis 
@@ -21,6 +40,37 @@ pub struct Nummatus {
   h_basepoint: PublicKey,                     //h which is computed at height j of Quisquis blockchain
 }
 
+// Why a Nummatus proof was rejected when checked against the ledger
+#[derive(Debug)]
+pub enum LedgerVerifyError {
+  // Anonymity-set positions whose (pubkey, commitment) is not a live account at the height
+  UnknownEntries { height: u64, indices: Vec<usize> },
+  // Anonymity-set positions repeating an earlier entry, which would count its amount twice
+  DuplicateEntries { indices: Vec<usize> },
+  // Positions of owned accounts whose amount is above the discrete-log table
+  // bound, so the exchange cannot open them until the table is enlarged
+  AmountsOutOfRange { indices: Vec<usize> },
+  // Every entry is on the ledger but a NummatusPoK does not verify
+  InvalidProof,
+}
+
+impl fmt::Display for LedgerVerifyError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      LedgerVerifyError::UnknownEntries { height, indices } =>
+        write!(f, "{} anonymity-set entries are not on the ledger at height {}: {:?}", indices.len(), height, indices),
+      LedgerVerifyError::DuplicateEntries { indices } =>
+        write!(f, "{} anonymity-set entries are duplicates: {:?}", indices.len(), indices),
+      LedgerVerifyError::AmountsOutOfRange { indices } =>
+        write!(f, "{} owned amounts are above the discrete-log table bound: {:?}", indices.len(), indices),
+      LedgerVerifyError::InvalidProof =>
+        write!(f, "Nummatus proof does not verify"),
+    }
+  }
+}
+
+impl Error for LedgerVerifyError {}
+
 impl Nummatus {
   pub fn new(anon_list_size: usize) -> Nummatus {
     let zeropk = PublicKey::new();
@@ -37,25 +87,165 @@ impl Nummatus {
   }
 
   pub fn verify(&self) -> bool {
+    self.verify_with_nonce(None)
+  }
 
-    assert!(self.commitment_list.len() == self.pubkey_list.len());
-    assert!(self.commitment_list.len() == self.pedersen_com_list.len());
-    assert!(self.commitment_list.len() == self.pok_list.len());
-    assert!(self.commitment_list.len() != 0);
+  // Verifies a proof made for the audit with this nonce
+  pub fn verify_with_nonce(&self, nonce: Option<&Nonce>) -> bool {
 
-    for i in 0..self.commitment_list.len() {
-      if NummatusPoK::verify_pok(
+    // An empty proof, or one whose lists do not line up, never verifies
+    let len = self.commitment_list.len();
+    if len == 0 || self.pubkey_list.len() != len || self.pedersen_com_list.len() != len || self.pok_list.len() != len {
+      return false;
+    }
+
+    for i in 0..len {
+      if !NummatusPoK::verify_pok(
         self.pubkey_list[i],
         self.commitment_list[i],
         self.pedersen_com_list[i],
         self.h_basepoint,
         self.pok_list[i].clone(),
-      ) == false {
+        nonce,
+      ) {
         return false;
       }
     }
     true
   }
+
+  pub fn h_basepoint(&self) -> PublicKey {
+    self.h_basepoint
+  }
+
+  // Product of every Pedersen commitment, g^(total reserves) * h^(sum of
+  // blinding factors); None if it is the identity
+  pub fn reserve_commitment(&self) -> Option<PublicKey> {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    PublicKey::from_combination(&secp_inst, self.pedersen_com_list.iter().collect()).ok()
+  }
+
+  // Verifies the proof and checks that every anonymity-set entry is a
+  // distinct account alive on the ledger at the snapshot's height, so that
+  // the exchange cannot add accounts of its own invention
+  pub fn verify_against_ledger(&self, snapshot: &LedgerSnapshot, nonce: Option<&Nonce>) -> Result<(), LedgerVerifyError> {
+
+    let ledger_entries: HashSet<(PublicKey, PublicKey, PublicKey, PublicKey)> = snapshot.accounts.iter()
+      .map(|account| (account.pubkey.x, account.pubkey.y, account.commitment.x, account.commitment.y))
+      .collect();
+
+    let mut seen = HashSet::new();
+    let mut unknown = Vec::new();
+    let mut duplicates = Vec::new();
+
+    for (i, (pubkey, commitment)) in self.pubkey_list.iter().zip(&self.commitment_list).enumerate() {
+      let entry = (pubkey.x, pubkey.y, commitment.x, commitment.y);
+      if !ledger_entries.contains(&entry) {
+        unknown.push(i);
+      } else if !seen.insert(entry) {
+        duplicates.push(i);
+      }
+    }
+
+    if !unknown.is_empty() {
+      return Err(LedgerVerifyError::UnknownEntries { height: snapshot.height, indices: unknown });
+    }
+    if !duplicates.is_empty() {
+      return Err(LedgerVerifyError::DuplicateEntries { indices: duplicates });
+    }
+    if !self.verify_with_nonce(nonce) {
+      return Err(LedgerVerifyError::InvalidProof);
+    }
+    Ok(())
+  }
+
+  // Writes h followed by one fixed-size record per anonymity-set entry,
+  // the format read back by NummatusStreamVerifier
+  pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+    write_point(&secp_inst, writer, &self.h_basepoint)?;
+    for i in 0..self.pubkey_list.len() {
+      write_record(
+        &secp_inst,
+        writer,
+        &self.pubkey_list[i],
+        &self.commitment_list[i],
+        &self.pedersen_com_list[i],
+        &self.pok_list[i],
+      )?;
+    }
+    Ok(())
+  }
+
+  // Reads a proof written by write_to, with records up to the end of the
+  // stream. Every point and scalar must be a valid encoding, and a proof
+  // without records is rejected with InvalidData.
+  pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Nummatus> {
+    let secp_inst = Secp256k1::without_caps();
+
+    let mut proof = Nummatus::new(0);
+    proof.g_basepoint = PublicKey::from_slice(&secp_inst, &GENERATOR_G).unwrap();
+    proof.h_basepoint = read_point(&secp_inst, reader)?;
+    while let Some((pubkey, commitment, pedersen_com, pok)) = read_record(&secp_inst, reader)? {
+      proof.pubkey_list.push(pubkey);
+      proof.commitment_list.push(commitment);
+      proof.pedersen_com_list.push(pedersen_com);
+      proof.pok_list.push(pok);
+    }
+
+    if proof.pok_list.is_empty() {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "Nummatus proof without records"));
+    }
+    Ok(proof)
+  }
+}
+
+// One entry of the anonymity set as seen by the exchange: either an account
+// it owns, for which it knows the secret key and amount, or someone else's
+pub enum AnonymityEntry {
+  Owned {
+    pubkey: QPublicKey,
+    commitment: QPublicKey,
+    key: SecretKey,
+    amount: u64,
+  },
+  Foreign {
+    pubkey: QPublicKey,
+    commitment: QPublicKey,
+  },
+}
+
+//placeholder statement of the given size over g and h; an exchange stores no
+//PoKs, they are made afresh for every proof
+fn statement(secp_inst: &Secp256k1, anon_list_size: usize) -> Nummatus {
+  Nummatus {
+    pubkey_list: vec![QPublicKey::new(); anon_list_size],
+    commitment_list: vec![QPublicKey::new(); anon_list_size],
+    pedersen_com_list: vec![PublicKey::new(); anon_list_size],
+    pok_list: Vec::new(),
+    g_basepoint: PublicKey::from_slice(secp_inst, &GENERATOR_G).unwrap(),
+    h_basepoint: PublicKey::from_slice(secp_inst, &GENERATOR_H).unwrap(),
+  }
+}
+
+//Pedersen commitment of one entry and the decoy key it is proved with, from
+//its secret key and amount, or ZERO_KEY and 0 for a decoy. Owned entries and
+//decoys go through the same operations: a fresh r is drawn for both and
+//select_scalar picks g^v * h^k or g^0 * h^r.
+fn entry_commitment<R: Rng>(
+  secp_inst: &Secp256k1,
+  rng: &mut R,
+  g: PublicKey,
+  h: PublicKey,
+  key: &SecretKey,
+  amount: u64,
+  ) -> (PublicKey, SecretKey) {
+
+  let owned = 1 - scalar_is_zero(key);
+  let r = SecretKey::new(secp_inst, rng);
+  let pedersen_com = uniform_pedersen_commitment(secp_inst, rng, g, h, amount, select_scalar(owned, key, &r));
+  (pedersen_com, select_scalar(owned, &ZERO_KEY, &r))
 }
 
 pub struct NummatusExchange {
@@ -68,8 +258,8 @@ pub struct NummatusExchange {
 impl NummatusExchange {
   pub fn new(alist_size: usize, olist_size: usize) -> NummatusExchange  {
 
-    let mut nproof = Nummatus::new(alist_size);
     let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut nproof = statement(&secp_inst, alist_size);
     let mut okeys = Vec::new();
     let mut amounts = vec![0u64; alist_size];
     let mut dkeys = vec![ZERO_KEY; alist_size];
@@ -87,9 +277,6 @@ impl NummatusExchange {
     // Randomly permuting the own outputs
     okeys.shuffle(&mut rng);
 
-    nproof.g_basepoint = PublicKey::from_slice(&secp_inst, &GENERATOR_G).unwrap();
-    nproof.h_basepoint = PublicKey::from_slice(&secp_inst, &GENERATOR_H).unwrap();
-
     for i in 0..alist_size {   
 
       if okeys[i] != ZERO_KEY {
@@ -101,21 +288,17 @@ impl NummatusExchange {
 
         nproof.pubkey_list[i].x = PublicKey::from_slice(&secp_inst, &GENERATOR_G).unwrap();   //generating PublicKey from SecretKey
         nproof.pubkey_list[i].x.mul_assign(&secp_inst, &r1).unwrap();
-        nproof.pubkey_list[i].y = nproof.pubkey_list[i].x.clone();
+        nproof.pubkey_list[i].y = nproof.pubkey_list[i].x;
         nproof.pubkey_list[i].y.mul_assign(&secp_inst, &okeys[i]).unwrap();
 
 
-        nproof.commitment_list[i].x = nproof.pubkey_list[i].x.clone();       //generating commitment from PublicKey and amount
+        nproof.commitment_list[i].x = nproof.pubkey_list[i].x;       //generating commitment from PublicKey and amount
         nproof.commitment_list[i].x.mul_assign(&secp_inst, &r2).unwrap();
-        let mut v_g = nproof.g_basepoint.clone();
+        let mut v_g = nproof.g_basepoint;
         v_g.mul_assign(&secp_inst, &amount_to_key(&secp_inst, amounts[i])).unwrap();
-        let mut r2_d = nproof.pubkey_list[i].y.clone();
+        let mut r2_d = nproof.pubkey_list[i].y;
         r2_d.mul_assign(&secp_inst, &r2).unwrap();
         nproof.commitment_list[i].y = PublicKey::from_combination(&secp_inst, vec![&v_g, &r2_d]).unwrap();
-
-        let mut k_h = nproof.h_basepoint.clone();                            //generating Pedersen commitment from amount and blinding factor
-        k_h.mul_assign(&secp_inst, &okeys[i].clone()).unwrap();
-        nproof.pedersen_com_list[i] = PublicKey::from_combination(&secp_inst, vec![&v_g, &k_h]).unwrap();
       } 
 
       else {
@@ -128,11 +311,11 @@ impl NummatusExchange {
         nproof.pubkey_list[i].y = PublicKey::from_secret_key(&secp_inst, &temp_sk_py).unwrap();
         nproof.commitment_list[i].x = PublicKey::from_secret_key(&secp_inst, &temp_sk_cx).unwrap();  //generating commitment randomly
         nproof.commitment_list[i].y = PublicKey::from_secret_key(&secp_inst, &temp_sk_cy).unwrap();
-        
-        dkeys[i] = SecretKey::new(&secp_inst, &mut rng);  
-        nproof.pedersen_com_list[i] = nproof.h_basepoint.clone();                //generating Pedersen commitment from blinding factor                     
-        nproof.pedersen_com_list[i].mul_assign(&secp_inst, &dkeys[i]).unwrap();
       }
+
+      let (pedersen_com, dkey) = entry_commitment(&secp_inst, &mut rng, nproof.g_basepoint, nproof.h_basepoint, &okeys[i], amounts[i]);
+      nproof.pedersen_com_list[i] = pedersen_com;
+      dkeys[i] = dkey;
     }
 
     NummatusExchange  {
@@ -143,37 +326,304 @@ impl NummatusExchange {
     }
   }
 
-  pub fn generate_proof(&mut self) -> Nummatus {
+  // Uses every account alive at the given height of the ledger as the
+  // anonymity set and proves ownership of those belonging to owner. The
+  // amounts of owned accounts are decrypted from their commitments, so table
+  // must cover the largest owned balance, or AmountsOutOfRange is returned.
+  pub fn from_ledger(ledger: &Ledger, owner: usize, height: u64, table: &DiscreteLogTable) -> Result<NummatusExchange, LedgerVerifyError> {
+    NummatusExchange::from_ledger_with_selector(ledger, owner, height, table, &WholeLedger)
+  }
+
+  // As from_ledger, but with the anonymity set chosen by selector
+  pub fn from_ledger_with_selector(
+    ledger: &Ledger,
+    owner: usize,
+    height: u64,
+    table: &DiscreteLogTable,
+    selector: &dyn AnonymitySetSelector,
+    ) -> Result<NummatusExchange, LedgerVerifyError> {
+
+    let mut rng = thread_rng();
+    let owned = ledger.owned_accounts(owner, height);
+    let anonymity_set = selector.select(ledger, height, &owned, &mut rng);
+    let alist_size = anonymity_set.len();
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut nproof = statement(&secp_inst, alist_size);
+    let mut okeys = vec![ZERO_KEY; alist_size];
+    let mut dkeys = vec![ZERO_KEY; alist_size];
+    let mut out_of_range = Vec::new();
+
+    for (i, &id) in anonymity_set.iter().enumerate() {
+      let account = ledger.account(id);
+      nproof.pubkey_list[i] = account.pubkey;
+      nproof.commitment_list[i] = account.commitment;
+
+      let mut amount = 0;
+      if ledger.owner(account.id) == owner {
+        okeys[i] = ledger.key(account.id);
+        amount = match decrypt_commitment(&account.commitment, &okeys[i], table) {
+          Some(amount) => amount,
+          None => {
+            out_of_range.push(i);
+            continue;
+          },
+        };
+      }
+
+      let (pedersen_com, dkey) = entry_commitment(&secp_inst, &mut rng, nproof.g_basepoint, nproof.h_basepoint, &okeys[i], amount);
+      nproof.pedersen_com_list[i] = pedersen_com;
+      dkeys[i] = dkey;
+    }
+
+    if !out_of_range.is_empty() {
+      return Err(LedgerVerifyError::AmountsOutOfRange { indices: out_of_range });
+    }
+    Ok(NummatusExchange  {
+      anon_list_size: alist_size,
+      nummatus_proof: nproof,
+      own_keys: okeys,
+      decoy_keys: dkeys,
+    })
+  }
+
+  // Builds an exchange from its secret keys alone, with the whole snapshot as
+  // the anonymity set. Owned accounts are found by scanning the snapshot with
+  // num_threads threads; every other account becomes a decoy.
+  pub fn from_keys(
+    snapshot: &LedgerSnapshot,
+    keys: &[SecretKey],
+    table: &DiscreteLogTable,
+    num_threads: usize,
+    ) -> Result<NummatusExchange, LedgerVerifyError> {
+    NummatusExchange::from_scan(snapshot, scan_parallel(snapshot, keys, table, num_threads))
+  }
+
+  // As from_keys, with the keys regenerated from the exchange's keychain
+  pub fn from_keychain(
+    snapshot: &LedgerSnapshot,
+    keychain: &Keychain,
+    table: &DiscreteLogTable,
+    gap_limit: u32,
+    num_threads: usize,
+    ) -> Result<NummatusExchange, LedgerVerifyError> {
+    NummatusExchange::from_scan(snapshot, keychain.scan(snapshot, table, gap_limit, num_threads))
+  }
+
+  //whole snapshot as anonymity set, with the scanned accounts as owned outputs
+  fn from_scan(snapshot: &LedgerSnapshot, matches: Vec<ScanMatch>) -> Result<NummatusExchange, LedgerVerifyError> {
+
+    let mut rng = thread_rng();
+    let alist_size = snapshot.accounts.len();
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut nproof = statement(&secp_inst, alist_size);
+    let mut okeys = vec![ZERO_KEY; alist_size];
+    let mut dkeys = vec![ZERO_KEY; alist_size];
+    let mut amounts = vec![0u64; alist_size];
+
+    let out_of_range: Vec<usize> = matches.iter().filter(|found| found.amount.is_none()).map(|found| found.index).collect();
+    if !out_of_range.is_empty() {
+      return Err(LedgerVerifyError::AmountsOutOfRange { indices: out_of_range });
+    }
+
+    for found in matches {
+      amounts[found.index] = found.amount.unwrap();
+      okeys[found.index] = found.key;
+    }
+
+    for (i, account) in snapshot.accounts.iter().enumerate() {
+      nproof.pubkey_list[i] = account.pubkey;
+      nproof.commitment_list[i] = account.commitment;
+
+      let (pedersen_com, dkey) = entry_commitment(&secp_inst, &mut rng, nproof.g_basepoint, nproof.h_basepoint, &okeys[i], amounts[i]);
+      nproof.pedersen_com_list[i] = pedersen_com;
+      dkeys[i] = dkey;
+    }
+
+    Ok(NummatusExchange  {
+      anon_list_size: alist_size,
+      nummatus_proof: nproof,
+      own_keys: okeys,
+      decoy_keys: dkeys,
+    })
+  }
+
+  // Positions of the owned outputs in the anonymity set
+  pub fn owned_indices(&self) -> Vec<usize> {
+    (0..self.anon_list_size).filter(|&i| self.own_keys[i] != ZERO_KEY).collect()
+  }
+
+  // Decrypts the commitment of every owned output, returning (index, amount)
+  // pairs in anonymity-set order; the amount is None if it exceeds the table bound
+  pub fn decrypt_amounts(&self, table: &DiscreteLogTable) -> Vec<(usize, Option<u64>)> {
+    (0..self.anon_list_size)
+      .filter(|&i| self.own_keys[i] != ZERO_KEY)
+      .map(|i| (i, decrypt_commitment(&self.nummatus_proof.commitment_list[i], &self.own_keys[i], table)))
+      .collect()
+  }
+
+  // Opening (total, blinding) of the proof's reserve commitment: the sum of
+  // the owned amounts and of every key and decoy blinding factor. Fails with
+  // AmountsOutOfRange if an owned amount is above the table bound.
+  pub fn reserve_opening(&self, table: &DiscreteLogTable) -> Result<(u64, SecretKey), LedgerVerifyError> {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut total = 0;
+    let mut blinding = ZERO_KEY;
+    let mut out_of_range = Vec::new();
 
     for i in 0..self.anon_list_size {
       if self.own_keys[i] != ZERO_KEY {
-
-        self.nummatus_proof.pok_list[i] = NummatusPoK::create_pok_from_representation(
-                                            self.nummatus_proof.pubkey_list[i],
-                                            self.nummatus_proof.commitment_list[i],
-                                            self.nummatus_proof.pedersen_com_list[i],
-                                            self.own_keys[i].clone(),
-                                            self.nummatus_proof.h_basepoint,      
-                                          );
+        match decrypt_commitment(&self.nummatus_proof.commitment_list[i], &self.own_keys[i], table) {
+          Some(amount) => total += amount,
+          None => out_of_range.push(i),
+        }
+        blinding.add_assign(&secp_inst, &self.own_keys[i]).unwrap();
       } else {
-        self.nummatus_proof.pok_list[i] = NummatusPoK::create_pok_from_decoy(
-                                            self.nummatus_proof.pubkey_list[i],
-                                            self.nummatus_proof.commitment_list[i],
-                                            self.nummatus_proof.pedersen_com_list[i],
-                                            self.decoy_keys[i].clone(),
-                                            self.nummatus_proof.h_basepoint,     
-                                          );
-      } 
-    } 
+        blinding.add_assign(&secp_inst, &self.decoy_keys[i]).unwrap();
+      }
+    }
 
-    Nummatus {
-      pubkey_list : self.nummatus_proof.pubkey_list.clone(),
-      commitment_list : self.nummatus_proof.commitment_list.clone(),
-      pedersen_com_list : self.nummatus_proof.pedersen_com_list.clone(),
-      pok_list: self.nummatus_proof.pok_list.clone(),
+    if !out_of_range.is_empty() {
+      return Err(LedgerVerifyError::AmountsOutOfRange { indices: out_of_range });
+    }
+    Ok((total, blinding))
+  }
+
+  pub fn generate_proof(&mut self) -> Nummatus {
+    self.generate_proof_with_nonce(None)
+  } // end generate_proof
+
+  // Proof for the audit with this nonce, which only verifies with the same nonce
+  pub fn generate_proof_with_nonce(&mut self, nonce: Option<&Nonce>) -> Nummatus {
+    self.generate_proof_with_timings(nonce).0
+  } // end generate_proof_with_nonce
+
+  // As generate_proof_with_nonce, also returning how long the PoK of each
+  // index took, to measure whether proving time depends on ownership
+  pub fn generate_proof_with_timings(&mut self, nonce: Option<&Nonce>) -> (Nummatus, Vec<Duration>) {
+
+    let mut proof = Nummatus {
+      pubkey_list: Vec::with_capacity(self.anon_list_size),
+      commitment_list: Vec::with_capacity(self.anon_list_size),
+      pedersen_com_list: Vec::with_capacity(self.anon_list_size),
+      pok_list: Vec::with_capacity(self.anon_list_size),
       g_basepoint: self.nummatus_proof.g_basepoint,
       h_basepoint: self.nummatus_proof.h_basepoint,
+    };
+    let mut timings = Vec::with_capacity(self.anon_list_size);
+
+    self.prove_records(nonce, |pubkey, commitment, pedersen_com, pok, time| {
+      proof.pubkey_list.push(*pubkey);
+      proof.commitment_list.push(*commitment);
+      proof.pedersen_com_list.push(*pedersen_com);
+      proof.pok_list.push(pok);
+      timings.push(time);
+      Ok(())
+    }).unwrap();                                                     //the sink never fails
+    (proof, timings)
+  } // end generate_proof_with_timings
+
+  // Proves the anonymity set one entry at a time, handing each record and
+  // the time its PoK took to sink. Nothing is stored in the exchange, so
+  // every proof is built straight from its lists without cloning them.
+  fn prove_records<F>(&self, nonce: Option<&Nonce>, mut sink: F) -> io::Result<()>
+    where F: FnMut(&QPublicKey, &QPublicKey, &PublicKey, NummatusPoK, Duration) -> io::Result<()> {
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut rng = thread_rng();
+    let proof = &self.nummatus_proof;
+
+    for i in 0..self.anon_list_size {
+      let pok_start = Instant::now();
+      let pok = NummatusPoK::create_pok(
+        &secp_inst,
+        &mut rng,
+        proof.pubkey_list[i],
+        proof.commitment_list[i],
+        proof.pedersen_com_list[i],
+        &self.own_keys[i],
+        &self.decoy_keys[i],
+        proof.h_basepoint,
+        nonce,
+      );
+      let time = pok_start.elapsed();
+      sink(&proof.pubkey_list[i], &proof.commitment_list[i], &proof.pedersen_com_list[i], pok, time)?;
     }
-  } // end generate_proof
+    Ok(())
+  } // end prove_records
+
+  // Proof anchored to the Merkle root of the snapshot, with a membership
+  // proof per anonymity-set entry for auditors that only hold the root.
+  // Fails with UnknownEntries if the anonymity set is not in the snapshot.
+  pub fn generate_anchored_proof(&mut self, snapshot: &LedgerSnapshot, nonce: Option<&Nonce>) -> Result<AnchoredNummatus, LedgerVerifyError> {
+    AnchoredNummatus::new(self.generate_proof_with_nonce(nonce), &MerkleTree::from_snapshot(snapshot))
+  } // end generate_anchored_proof
+
+  // Same statement as generate_proof, but with one global challenge shared by all outputs
+  pub fn generate_compact_proof(&self) -> CompactNummatus {
+    self.generate_compact_proof_with_nonce(None)
+  } // end generate_compact_proof
+
+  pub fn generate_compact_proof_with_nonce(&self, nonce: Option<&Nonce>) -> CompactNummatus {
+    CompactNummatus::create(
+      self.nummatus_proof.g_basepoint,
+      self.nummatus_proof.h_basepoint,
+      self.nummatus_proof.pubkey_list.clone(),
+      self.nummatus_proof.commitment_list.clone(),
+      self.nummatus_proof.pedersen_com_list.clone(),
+      &self.own_keys,
+      &self.decoy_keys,
+      nonce,
+    )
+  } // end generate_compact_proof_with_nonce
+
+  // Prover for an interactive session over the exchange's current anonymity set
+  pub fn interactive_prover(&self) -> NummatusProver {
+    NummatusProver::new(
+      self.nummatus_proof.h_basepoint,
+      self.nummatus_proof.pubkey_list.clone(),
+      self.nummatus_proof.commitment_list.clone(),
+      self.nummatus_proof.pedersen_com_list.clone(),
+      self.own_keys.clone(),
+      self.decoy_keys.clone(),
+    )
+  } // end interactive_prover
+
+  // Writes the proof for the exchange's current anonymity set in the format
+  // read by NummatusStreamVerifier, without cloning any of the lists
+  pub fn write_proof<W: Write>(&self, writer: &mut W, nonce: Option<&Nonce>) -> io::Result<()> {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+    write_point(&secp_inst, writer, &self.nummatus_proof.h_basepoint)?;
+    self.prove_records(nonce, |pubkey, commitment, pedersen_com, pok, _| {
+      write_record(&secp_inst, writer, pubkey, commitment, pedersen_com, &pok)
+    })
+  } // end write_proof
+
+  // Proves an anonymity set supplied one entry at a time, writing h and then
+  // one record per entry so that memory use does not grow with the set.
+  // Returns the number of records written.
+  pub fn generate_proof_stream<I, W>(entries: I, writer: &mut W, nonce: Option<&Nonce>) -> io::Result<usize>
+    where I: IntoIterator<Item = AnonymityEntry>, W: Write {
+
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut rng = thread_rng();
+    let g_basepoint = PublicKey::from_slice(&secp_inst, &GENERATOR_G).unwrap();
+    let h_basepoint = PublicKey::from_slice(&secp_inst, &GENERATOR_H).unwrap();
+    let mut num_records = 0;
+
+    write_point(&secp_inst, writer, &h_basepoint)?;
+    for entry in entries {
+      let (pubkey, commitment, key, amount) = match entry {
+        AnonymityEntry::Owned { pubkey, commitment, key, amount } => (pubkey, commitment, key, amount),
+        AnonymityEntry::Foreign { pubkey, commitment } => (pubkey, commitment, ZERO_KEY, 0),
+      };
+      let (pedersen_com, dkey) = entry_commitment(&secp_inst, &mut rng, g_basepoint, h_basepoint, &key, amount);
+
+      let pok = NummatusPoK::create_pok(&secp_inst, &mut rng, pubkey, commitment, pedersen_com, &key, &dkey, h_basepoint, nonce);
+      write_record(&secp_inst, writer, &pubkey, &commitment, &pedersen_com, &pok)?;
+      num_records += 1;
+    }
+    Ok(num_records)
+  } // end generate_proof_stream
 
 } // end NummatusExchange implementation 
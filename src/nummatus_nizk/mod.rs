@@ -1,26 +1,38 @@
-use rand::thread_rng;
+use std::io::{self, Read, Write};
+
+use rand::{thread_rng, Rng};
 use secp256k1zkp as secp;
 use secp::Secp256k1;
-use secp::key::{SecretKey, PublicKey, ZERO_KEY};
+use secp::key::{SecretKey, PublicKey, ZERO_KEY, ONE_KEY};
 
 use crate::misc::QPublicKey;
 use crate::misc::MINUS_ONE_KEY;
-use crate::misc::single_base_product;
 use crate::misc::double_base_product;
 use crate::misc::ratio;
+use crate::misc::{checked_double_base_product, checked_ratio};
+use crate::misc::{is_valid_point, is_valid_scalar};
 use crate::misc::hash_special_tx;
-use crate::misc::a_minus_bx;
+use crate::misc::{a_minus_bx, checked_a_minus_bx};
+use crate::misc::{scalar_is_zero, select_scalar};
+use crate::misc::{write_scalar, read_scalar};
+use crate::misc::SCALAR_SIZE;
+use crate::misc::Nonce;
 
 #[derive (Clone)]
 pub struct NummatusPoK {
-	e1 : SecretKey,
-	e2 : SecretKey,
-	s1 : SecretKey,
-	s2 : SecretKey,
+	pub(crate) e1 : SecretKey,
+	pub(crate) e2 : SecretKey,
+	pub(crate) s1 : SecretKey,
+	pub(crate) s2 : SecretKey,
 }
 
+/// Size of a serialized NummatusPoK
+pub const NUMMATUS_POK_SIZE: usize = 4 * SCALAR_SIZE;
+
 impl NummatusPoK {
 
+	// All-zero placeholder that verify_pok rejects, like QPublicKey::new
+	#[allow(clippy::new_without_default)]
 	pub fn new() -> NummatusPoK {
 		NummatusPoK {
 			e1 : ZERO_KEY,
@@ -36,49 +48,11 @@ impl NummatusPoK {
 		pedersen_com : PublicKey,
 		beta : SecretKey,
 		h_j : PublicKey,
+		nonce : Option<&Nonce>,
 		) -> NummatusPoK {
 
-		let mut rng = thread_rng();
 	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
-
-	    let mut rpok = NummatusPoK::new();
-	    let r2 = SecretKey::new(&secp_inst, &mut rng);
-	    rpok.e1 = SecretKey::new(&secp_inst, &mut rng);
-	    rpok.s1 = SecretKey::new(&secp_inst, &mut rng);
-
-	    //v1 = a^s1 + b^e1    
-	    let v1 = double_base_product(&secp_inst, pubkey.x.clone(), pubkey.y.clone(), rpok.s1.clone(), rpok.e1.clone());
-
-	    //v2 = (h*c^-1)^s1 * (p*d^-1)^e1  
-	    let p_minus_d = ratio(&secp_inst, pedersen_com.clone(), commitment.y.clone());
-	    let h_minus_c = ratio(&secp_inst, h_j.clone(), commitment.x.clone());
-	    let v2 = double_base_product(&secp_inst, h_minus_c.clone(), p_minus_d.clone(), rpok.s1.clone(), rpok.e1.clone());
-
-	    //v3 = h^r2
-	    let v3 = single_base_product(&secp_inst, h_j.clone(), r2.clone());
-
-	    let hash_scalar = hash_special_tx(&secp_inst,
-	    								h_j,
-	    								pubkey.x.clone(),          //a
-	    								pubkey.y.clone(),          //b
-	    								commitment.x.clone(),      //c
-	    								commitment.y.clone(),      //d
-	    								pedersen_com.clone(),          //p
-	    								v1.clone(),
-	    								v2.clone(),
-	    								v3.clone()
-	    								);
-
-	    // Calculation of -e_1
-	    let mut minus_e1 = rpok.e1.clone();
-	    minus_e1.mul_assign(&secp_inst, &MINUS_ONE_KEY).unwrap();
-
-	    // Calculation of e_2
-	    rpok.e2 = hash_scalar;                                      // e_2 = H(h_j, a, b, c, d, p, v1, v2, v3)
-	    rpok.e2.add_assign(&secp_inst, &minus_e1).unwrap();         // e_2 = H(h_j, a, b, c, d, p, v1, v2, v3) - e_1
-
-	    rpok.s2 = a_minus_bx(&secp_inst, r2.clone(), rpok.e2.clone(), beta);
-	    rpok
+	    NummatusPoK::create_pok(&secp_inst, &mut thread_rng(), pubkey, commitment, pedersen_com, &ZERO_KEY, &beta, h_j, nonce)
 	}
 
 	pub fn create_pok_from_representation (
@@ -87,48 +61,67 @@ impl NummatusPoK {
 		pedersen_com : PublicKey,
 		alpha : SecretKey,
 		h_j : PublicKey,
+		nonce : Option<&Nonce>,
 		) -> NummatusPoK {
 
-		let mut rng = thread_rng();
 	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+	    NummatusPoK::create_pok(&secp_inst, &mut thread_rng(), pubkey, commitment, pedersen_com, &alpha, &ZERO_KEY, h_j, nonce)
+	}
 
-	    let mut rpok = NummatusPoK::new();
-	    let r1 = SecretKey::new(&secp_inst, &mut rng);
-	    rpok.e2 = SecretKey::new(&secp_inst, &mut rng);
-	    rpok.s2 = SecretKey::new(&secp_inst, &mut rng);
+	// PoK of one output: own_key is its secret key, or ZERO_KEY for a decoy
+	// whose Pedersen blinding factor is decoy_key. Owned outputs and decoys
+	// run the same sequence of group operations on uniformly random
+	// exponents, so proving time and memory accesses do not reveal which
+	// outputs are owned.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_pok<R: Rng> (
+		secp_inst : &Secp256k1,
+		rng : &mut R,
+		pubkey : QPublicKey,
+		commitment : QPublicKey,
+		pedersen_com : PublicKey,
+		own_key : &SecretKey,
+		decoy_key : &SecretKey,
+		h_j : PublicKey,
+		nonce : Option<&Nonce>,
+		) -> NummatusPoK {
 
-	    //v1 = a^r1
-	    let v1 = single_base_product(&secp_inst, pubkey.x.clone(), r1.clone());
+	    let prover_nonces = random_nonces(secp_inst, rng);
+	    NummatusPoK::create_pok_with_nonces(secp_inst, pubkey, commitment, pedersen_com, own_key, decoy_key, h_j, &prover_nonces, nonce)
+	}
 
-	    //v2 = (h*c^-1)^r1
-	    let h_minus_c = ratio(&secp_inst, h_j.clone(), commitment.x.clone());
-	    let v2 = single_base_product(&secp_inst, h_minus_c.clone(), r1.clone());
+	// As create_pok with the prover's randomness (x1, y1, x2, y2) given
+	// explicitly, for known-answer tests. Never reuse it across proofs: two
+	// PoKs with the same prover nonces reveal the witness.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_pok_with_nonces (
+		secp_inst : &Secp256k1,
+		pubkey : QPublicKey,
+		commitment : QPublicKey,
+		pedersen_com : PublicKey,
+		own_key : &SecretKey,
+		decoy_key : &SecretKey,
+		h_j : PublicKey,
+		prover_nonces : &[SecretKey; 4],
+		nonce : Option<&Nonce>,
+		) -> NummatusPoK {
 
-	    //v3 = h^s2 * p^e2
-	    let v3 = double_base_product(&secp_inst, h_j.clone(), pedersen_com.clone(), rpok.s2.clone(), rpok.e2.clone());
+	    let ((v1, v2, v3), state) = first_message_with_nonces(secp_inst, pubkey, commitment, pedersen_com, h_j, own_key, decoy_key, prover_nonces);
 
-	    let hash_scalar = hash_special_tx(&secp_inst,
+	    let hash_scalar = hash_special_tx(secp_inst,
 	    								h_j,
-	    								pubkey.x.clone(),          //a
-	    								pubkey.y.clone(),          //b
-	    								commitment.x.clone(),      //c
-	    								commitment.y.clone(),      //d
-	    								pedersen_com.clone(),          //p
-	    								v1.clone(),
-	    								v2.clone(),
-	    								v3.clone(),
+	    								pubkey.x,          //a
+	    								pubkey.y,          //b
+	    								commitment.x,      //c
+	    								commitment.y,      //d
+	    								pedersen_com,          //p
+	    								v1,
+	    								v2,
+	    								v3,
+	    								nonce,
 	    								);
 
-	    // Calculation of -e_2
-	    let mut minus_e2 = rpok.e2.clone();
-	    minus_e2.mul_assign(&secp_inst, &MINUS_ONE_KEY).unwrap();
-
-	    // Calculation of e_1
-	    rpok.e1 = hash_scalar;                                      // e_1 = H(h_j, a, b, c, d, p, v1, v2, v3)
-	    rpok.e1.add_assign(&secp_inst, &minus_e2).unwrap();         // e_1 = H(h_j, a, b, c, d, p, v1, v2, v3) - e_2
-
-	    rpok.s1 = a_minus_bx(&secp_inst, r1.clone(), rpok.e1.clone(), alpha);
-	    rpok
+	    response(secp_inst, state, &hash_scalar)                    // e_1 + e_2 = H(h_j, a, b, c, d, p, v1, v2, v3)
 	}
 
 	pub fn verify_pok (
@@ -137,36 +130,282 @@ impl NummatusPoK {
 		pedersen_com : PublicKey,
 		h_j : PublicKey,
 		rpok : NummatusPoK,
+		nonce : Option<&Nonce>,
 		) -> bool {
 
 	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
 
-	    //v1 = a^s1 * b^e1    
-	    let v1 = double_base_product(&secp_inst, pubkey.x.clone(), pubkey.y.clone(), rpok.s1.clone(), rpok.e1.clone());
-
-	    //v2 = (h*c^-1)^s1 * (p*d^-1)^e1  
-	    let p_minus_d = ratio(&secp_inst, pedersen_com.clone(), commitment.y.clone());
-	    let h_minus_c = ratio(&secp_inst, h_j.clone(), commitment.x.clone());
-	    let v2 = double_base_product(&secp_inst, h_minus_c.clone(), p_minus_d.clone(), rpok.s1.clone(), rpok.e1.clone());
+	    // Placeholder or off-curve points and out-of-range scalars never verify
+	    let points = [pubkey.x, pubkey.y, commitment.x, commitment.y, pedersen_com, h_j];
+	    let scalars = [&rpok.e1, &rpok.e2, &rpok.s1, &rpok.s2];
+	    if !points.iter().all(|point| is_valid_point(&secp_inst, point)) || !scalars.iter().all(|scalar| is_valid_scalar(&secp_inst, scalar)) {
+	    	return false;
+	    }
 
-	    //v3 = h^s2 * p^e2
-	    let v3 = double_base_product(&secp_inst, h_j.clone(), pedersen_com.clone(), rpok.s2.clone(), rpok.e2.clone());
+	    // A degenerate statement or PoK whose first messages would be the identity never verifies
+	    let (v1, v2, v3) = match recompute_first_message(&secp_inst, pubkey, commitment, pedersen_com, h_j, &rpok) {
+	    	Some(first_messages) => first_messages,
+	    	None => return false,
+	    };
 
 	    let hash_scalar = hash_special_tx(&secp_inst,
 	    								h_j,
-	    								pubkey.x.clone(),          //a
-	    								pubkey.y.clone(),          //b
-	    								commitment.x.clone(),      //c
-	    								commitment.y.clone(),      //d
-	    								pedersen_com.clone(),          //p
-	    								v1.clone(),
-	    								v2.clone(),
-	    								v3.clone(),
+	    								pubkey.x,          //a
+	    								pubkey.y,          //b
+	    								commitment.x,      //c
+	    								commitment.y,      //d
+	    								pedersen_com,          //p
+	    								v1,
+	    								v2,
+	    								v3,
+	    								nonce,
 	    								);
 
 	    let mut e_sum = rpok.e1.clone();
-	    e_sum.add_assign(&secp_inst, &rpok.e2).unwrap();
+	    if e_sum.add_assign(&secp_inst, &rpok.e2).is_err() {
+	    	return false;                                            // e1 + e2 = 0 is never a hash output
+	    }
 
 	    e_sum == hash_scalar    // comparing e1+e2 from NummatusPoK and evaluation of the scalar-hash
 	}
-}
\ No newline at end of file
+	//writes e1, e2, s1, s2 in that order
+	pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+	    write_scalar(writer, &self.e1)?;
+	    write_scalar(writer, &self.e2)?;
+	    write_scalar(writer, &self.s1)?;
+	    write_scalar(writer, &self.s2)
+	}
+
+	//reads e1, e2, s1, s2 in that order
+	pub fn read_from<R: Read>(secp_inst: &Secp256k1, reader: &mut R) -> io::Result<NummatusPoK> {
+	    Ok(NummatusPoK {
+	    	e1 : read_scalar(secp_inst, reader)?,
+	    	e2 : read_scalar(secp_inst, reader)?,
+	    	s1 : read_scalar(secp_inst, reader)?,
+	    	s2 : read_scalar(secp_inst, reader)?,
+	    })
+	}
+}
+
+// Full transcript (v1, v2, v3, e1, e2, s1, s2) of one output for the
+// challenge e = e1 + e2
+#[derive (Clone, Debug, PartialEq)]
+pub struct NummatusTranscript {
+	pub v1 : PublicKey,
+	pub v2 : PublicKey,
+	pub v3 : PublicKey,
+	pub e1 : SecretKey,
+	pub e2 : SecretKey,
+	pub s1 : SecretKey,
+	pub s2 : SecretKey,
+}
+
+impl NummatusTranscript {
+
+	// Transcript of a real PoK, with the first messages recomputed from its
+	// responses; None if they are the identity, as for p = d
+	pub fn from_pok (
+		pubkey : QPublicKey,
+		commitment : QPublicKey,
+		pedersen_com : PublicKey,
+		h_j : PublicKey,
+		rpok : &NummatusPoK,
+		) -> Option<NummatusTranscript> {
+
+	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+	    let (v1, v2, v3) = recompute_first_message(&secp_inst, pubkey, commitment, pedersen_com, h_j, rpok)?;
+
+	    Some(NummatusTranscript {
+	    	v1, v2, v3,
+	    	e1 : rpok.e1.clone(),
+	    	e2 : rpok.e2.clone(),
+	    	s1 : rpok.s1.clone(),
+	    	s2 : rpok.s2.clone(),
+	    })
+	}
+
+	// Honest-verifier simulator: an accepting transcript for the given
+	// challenge without knowing alpha or beta. Both branches are simulated the
+	// way the prover simulates its decoy branch, so the output does not depend
+	// on whether the entry is owned. In a real transcript e1, e2, s1 and s2
+	// are also uniform subject to e1 + e2 = e, and v1, v2, v3 are determined
+	// by them, so the two distributions are identical. None for statements
+	// that no transcript accepts, as for from_pok.
+	pub fn simulate<R: Rng> (
+		rng : &mut R,
+		pubkey : QPublicKey,
+		commitment : QPublicKey,
+		pedersen_com : PublicKey,
+		h_j : PublicKey,
+		challenge : &SecretKey,
+		) -> Option<NummatusTranscript> {
+
+	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+	    let e1 = SecretKey::new(&secp_inst, rng);
+	    let e2 = checked_a_minus_bx(&secp_inst, challenge.clone(), ONE_KEY, e1.clone())?;    // e_2 = e - e_1
+	    let s1 = SecretKey::new(&secp_inst, rng);
+	    let s2 = SecretKey::new(&secp_inst, rng);
+
+	    let rpok = NummatusPoK { e1, e2, s1, s2 };
+	    NummatusTranscript::from_pok(pubkey, commitment, pedersen_com, h_j, &rpok)
+	}
+
+	// The challenge e1 + e2 this transcript answers; None for e1 = -e2, which
+	// no verifier's challenge can be
+	pub fn challenge(&self) -> Option<SecretKey> {
+	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+	    let mut e_sum = self.e1.clone();
+	    e_sum.add_assign(&secp_inst, &self.e2).ok()?;
+	    Some(e_sum)
+	}
+
+	// Checks the verification equations of the three-move protocol for the
+	// transcript's own challenge; a Fiat-Shamir PoK additionally needs that
+	// challenge to be the hash of the first messages
+	pub fn is_accepting (
+		&self,
+		pubkey : QPublicKey,
+		commitment : QPublicKey,
+		pedersen_com : PublicKey,
+		h_j : PublicKey,
+		) -> bool {
+
+	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+	    recompute_first_message(&secp_inst, pubkey, commitment, pedersen_com, h_j, &self.to_pok()) == Some((self.v1, self.v2, self.v3))
+	}
+
+	// The PoK carried by this transcript
+	pub fn to_pok(&self) -> NummatusPoK {
+	    NummatusPoK {
+	    	e1 : self.e1.clone(),
+	    	e2 : self.e2.clone(),
+	    	s1 : self.s1.clone(),
+	    	s2 : self.s2.clone(),
+	    }
+	}
+}
+
+// Prover randomness for one output, kept between the first messages and the
+// responses when the challenge comes from outside the PoK (a global hash or
+// an interactive verifier). The same fields serve owned outputs and decoys.
+pub(crate) struct ProverState {
+	owned : u8,               //1 for an owned output, 0 for a decoy
+	r : SecretKey,            //randomness of the branch proved with the witness
+	e_sim : SecretKey,        //challenge share of the simulated branch
+	s_sim : SecretKey,        //response of the simulated branch
+	witness : SecretKey,      //alpha for an owned output, beta for a decoy
+}
+
+// First messages (v1, v2, v3) of one output: own_key is its secret key, or
+// ZERO_KEY for a decoy whose Pedersen blinding factor is decoy_key
+//
+// Both branches are computed as double-base products with fresh exponents.
+// An owned output has b = a^alpha and p*d^-1 = (h*c^-1)^alpha, so v1 and v2
+// are a^r1 and (h*c^-1)^r1 for r1 = x1 + alpha*y1, while (y2, x2) serve as
+// the simulated (e2, s2). A decoy has p = h^beta, so v3 is h^r2 for
+// r2 = x2 + beta*y2, while (y1, x1) serve as the simulated (e1, s1). Which
+// of the two applies is only ever used to select scalars, without branching.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn first_message<R: Rng> (
+	secp_inst : &Secp256k1,
+	rng : &mut R,
+	pubkey : QPublicKey,
+	commitment : QPublicKey,
+	pedersen_com : PublicKey,
+	h_j : PublicKey,
+	own_key : &SecretKey,
+	decoy_key : &SecretKey,
+	) -> ((PublicKey, PublicKey, PublicKey), ProverState) {
+
+	let prover_nonces = random_nonces(secp_inst, rng);
+	first_message_with_nonces(secp_inst, pubkey, commitment, pedersen_com, h_j, own_key, decoy_key, &prover_nonces)
+}
+
+//fresh prover randomness (x1, y1, x2, y2) for one output
+fn random_nonces<R: Rng>(secp_inst : &Secp256k1, rng : &mut R) -> [SecretKey; 4] {
+	[
+	    SecretKey::new(secp_inst, rng),
+	    SecretKey::new(secp_inst, rng),
+	    SecretKey::new(secp_inst, rng),
+	    SecretKey::new(secp_inst, rng),
+	]
+}
+
+// As first_message with the prover's randomness given as [x1, y1, x2, y2]
+#[allow(clippy::too_many_arguments)]
+fn first_message_with_nonces (
+	secp_inst : &Secp256k1,
+	pubkey : QPublicKey,
+	commitment : QPublicKey,
+	pedersen_com : PublicKey,
+	h_j : PublicKey,
+	own_key : &SecretKey,
+	decoy_key : &SecretKey,
+	prover_nonces : &[SecretKey; 4],
+	) -> ((PublicKey, PublicKey, PublicKey), ProverState) {
+
+	let owned = 1 - scalar_is_zero(own_key);
+	let witness = select_scalar(owned, own_key, decoy_key);
+
+	let [x1, y1, x2, y2] = prover_nonces.clone();
+
+	let p_minus_d = ratio(secp_inst, pedersen_com, commitment.y);
+	let h_minus_c = ratio(secp_inst, h_j, commitment.x);
+	let v1 = double_base_product(secp_inst, pubkey.x, pubkey.y, x1.clone(), y1.clone());      //v1 = a^x1 * b^y1
+	let v2 = double_base_product(secp_inst, h_minus_c, p_minus_d, x1.clone(), y1.clone());    //v2 = (h*c^-1)^x1 * (p*d^-1)^y1
+	let v3 = double_base_product(secp_inst, h_j, pedersen_com, x2.clone(), y2.clone());       //v3 = h^x2 * p^y2
+
+	let mut minus_witness = witness.clone();
+	minus_witness.mul_assign(secp_inst, &MINUS_ONE_KEY).unwrap();
+	let x = select_scalar(owned, &x1, &x2);
+	let y = select_scalar(owned, &y1, &y2);
+	let r = a_minus_bx(secp_inst, x, y, minus_witness);                      // r = x + w*y
+
+	let state = ProverState {
+	    owned,
+	    r,
+	    e_sim : select_scalar(owned, &y2, &y1),
+	    s_sim : select_scalar(owned, &x2, &x1),
+	    witness,
+	};
+	((v1, v2, v3), state)
+}
+
+// Splits the challenge e into e1 + e2 and answers for one output
+pub(crate) fn response(secp_inst : &Secp256k1, state : ProverState, challenge : &SecretKey) -> NummatusPoK {
+	let e_real = a_minus_bx(secp_inst, challenge.clone(), ONE_KEY, state.e_sim.clone());    // e_real = e - e_sim
+	let s_real = a_minus_bx(secp_inst, state.r, e_real.clone(), state.witness);             // s_real = r - e_real*w
+
+	NummatusPoK {
+	    e1 : select_scalar(state.owned, &e_real, &state.e_sim),
+	    e2 : select_scalar(state.owned, &state.e_sim, &e_real),
+	    s1 : select_scalar(state.owned, &s_real, &state.s_sim),
+	    s2 : select_scalar(state.owned, &state.s_sim, &s_real),
+	}
+}
+
+// Recomputes the first messages (v1, v2, v3) of one output from its responses,
+// or None if any intermediate point is the identity
+pub(crate) fn recompute_first_message (
+	secp_inst : &Secp256k1,
+	pubkey : QPublicKey,
+	commitment : QPublicKey,
+	pedersen_com : PublicKey,
+	h_j : PublicKey,
+	rpok : &NummatusPoK,
+	) -> Option<(PublicKey, PublicKey, PublicKey)> {
+
+	//v1 = a^s1 * b^e1
+	let v1 = checked_double_base_product(secp_inst, pubkey.x, pubkey.y, rpok.s1.clone(), rpok.e1.clone())?;
+
+	//v2 = (h*c^-1)^s1 * (p*d^-1)^e1
+	let p_minus_d = checked_ratio(secp_inst, pedersen_com, commitment.y)?;
+	let h_minus_c = checked_ratio(secp_inst, h_j, commitment.x)?;
+	let v2 = checked_double_base_product(secp_inst, h_minus_c, p_minus_d, rpok.s1.clone(), rpok.e1.clone())?;
+
+	//v3 = h^s2 * p^e2
+	let v3 = checked_double_base_product(secp_inst, h_j, pedersen_com, rpok.s2.clone(), rpok.e2.clone())?;
+
+	Some((v1, v2, v3))
+}
@@ -0,0 +1,194 @@
+use std::io::{self, Read, Write};
+
+use digest::Digest;
+use sha2::Sha256;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::PublicKey;
+
+use crate::misc::QPublicKey;
+use crate::misc::POINT_SIZE;
+use crate::misc::read_point;
+use crate::misc::read_record_or_eof;
+use crate::misc::serialize_point;
+use crate::misc::write_point;
+use crate::misc::Nonce;
+
+use crate::nummatus_nizk::NummatusPoK;
+use crate::nummatus_nizk::NUMMATUS_POK_SIZE;
+
+/// Size of one (pubkey, commitment, pedersen_com, pok) record on the wire
+pub const NUMMATUS_RECORD_SIZE: usize = 5 * POINT_SIZE + NUMMATUS_POK_SIZE;
+
+//writes one anonymity-set entry together with its NummatusPoK
+pub fn write_record<W: Write>(
+  secp_inst: &Secp256k1,
+  writer: &mut W,
+  pubkey: &QPublicKey,
+  commitment: &QPublicKey,
+  pedersen_com: &PublicKey,
+  pok: &NummatusPoK,
+  ) -> io::Result<()> {
+
+  pubkey.write_to(secp_inst, writer)?;
+  commitment.write_to(secp_inst, writer)?;
+  write_point(secp_inst, writer, pedersen_com)?;
+  pok.write_to(writer)
+}
+
+//reads one raw record, returning None if the stream ends cleanly before it
+fn read_record_bytes<R: Read>(reader: &mut R) -> io::Result<Option<[u8; NUMMATUS_RECORD_SIZE]>> {
+  let mut buf = [0u8; NUMMATUS_RECORD_SIZE];
+  if read_record_or_eof(reader, &mut buf)? {
+    Ok(Some(buf))
+  } else {
+    Ok(None)
+  }
+}
+
+//parses a raw record into its four components
+fn parse_record(secp_inst: &Secp256k1, mut bytes: &[u8]) -> io::Result<(QPublicKey, QPublicKey, PublicKey, NummatusPoK)> {
+  let pubkey = QPublicKey::read_from(secp_inst, &mut bytes)?;
+  let commitment = QPublicKey::read_from(secp_inst, &mut bytes)?;
+  let pedersen_com = read_point(secp_inst, &mut bytes)?;
+  let pok = NummatusPoK::read_from(secp_inst, &mut bytes)?;
+
+  Ok((pubkey, commitment, pedersen_com, pok))
+}
+
+//reads one record, returning None if the stream ends cleanly before it
+pub fn read_record<R: Read>(
+  secp_inst: &Secp256k1,
+  reader: &mut R,
+  ) -> io::Result<Option<(QPublicKey, QPublicKey, PublicKey, NummatusPoK)>> {
+
+  match read_record_bytes(reader)? {
+    Some(bytes) => parse_record(secp_inst, &bytes).map(Some),
+    None => Ok(None),
+  }
+}
+
+// Verifies a Nummatus proof one record at a time so that the anonymity set
+// never has to be held in memory. Alongside verification it accumulates the
+// product of all Pedersen commitments, which commits to the total reserves,
+// and a SHA-256 hash over h and every record seen.
+pub struct NummatusStreamVerifier {
+  secp_inst: Secp256k1,
+  h_basepoint: PublicKey,                     //h which is computed at height j of Quisquis blockchain
+  nonce: Option<Nonce>,                       //auditor nonce the records were made for
+  num_records: usize,
+  reserve_commitment: Option<PublicKey>,      //None while the running product is the identity
+  transcript: Sha256,
+}
+
+impl NummatusStreamVerifier {
+  pub fn new(h_j: PublicKey) -> NummatusStreamVerifier {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut transcript = Sha256::new();
+    transcript.input(serialize_point(&secp_inst, &h_j, true));
+
+    NummatusStreamVerifier {
+      secp_inst,
+      h_basepoint: h_j,
+      nonce: None,
+      num_records: 0,
+      reserve_commitment: None,
+      transcript,
+    }
+  }
+
+  // Only accepts records made for the audit with this nonce
+  pub fn with_nonce(mut self, nonce: Nonce) -> NummatusStreamVerifier {
+    self.nonce = Some(nonce);
+    self
+  }
+
+  // Reads h from the start of a stream written by Nummatus::write_to
+  pub fn from_header<R: Read>(reader: &mut R) -> io::Result<NummatusStreamVerifier> {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let h_j = read_point(&secp_inst, reader)?;
+
+    Ok(NummatusStreamVerifier::new(h_j))
+  }
+
+  pub fn num_records(&self) -> usize {
+    self.num_records
+  }
+
+  // Product of the Pedersen commitments of every record verified so far,
+  // or None if there were none or they multiply to the identity. Records
+  // are not checked for repeats, so this only commits to the reserves once
+  // the entries are known to be distinct live accounts, e.g. through
+  // Nummatus::verify_against_ledger or AnchoredNummatus::verify.
+  pub fn reserve_commitment(&self) -> Option<PublicKey> {
+    self.reserve_commitment
+  }
+
+  // Hash of h and every record verified so far
+  pub fn transcript_hash(&self) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&self.transcript.clone().result());
+    hash
+  }
+
+  // Verifies a single record; the accumulators are only updated if it is valid
+  pub fn verify_record(
+    &mut self,
+    pubkey: QPublicKey,
+    commitment: QPublicKey,
+    pedersen_com: PublicKey,
+    pok: NummatusPoK,
+    ) -> bool {
+
+    let mut bytes = Vec::with_capacity(NUMMATUS_RECORD_SIZE);
+    write_record(&self.secp_inst, &mut bytes, &pubkey, &commitment, &pedersen_com, &pok).unwrap();
+
+    self.absorb(&bytes, pubkey, commitment, pedersen_com, pok)
+  }
+
+  // Reads and verifies the next record. Returns None at a clean end of stream.
+  pub fn verify_next<R: Read>(&mut self, reader: &mut R) -> io::Result<Option<bool>> {
+    let bytes = match read_record_bytes(reader)? {
+      Some(bytes) => bytes,
+      None => return Ok(None),
+    };
+    let (pubkey, commitment, pedersen_com, pok) = parse_record(&self.secp_inst, &bytes)?;
+
+    Ok(Some(self.absorb(&bytes, pubkey, commitment, pedersen_com, pok)))
+  }
+
+  // Verifies records until the end of the stream, stopping at the first
+  // invalid one. An empty stream is rejected just like an empty Nummatus.
+  pub fn verify_all<R: Read>(&mut self, reader: &mut R) -> io::Result<bool> {
+    while let Some(valid) = self.verify_next(reader)? {
+      if !valid {
+        return Ok(false);
+      }
+    }
+
+    Ok(self.num_records != 0)
+  }
+
+  fn absorb(
+    &mut self,
+    bytes: &[u8],
+    pubkey: QPublicKey,
+    commitment: QPublicKey,
+    pedersen_com: PublicKey,
+    pok: NummatusPoK,
+    ) -> bool {
+
+    if !NummatusPoK::verify_pok(pubkey, commitment, pedersen_com, self.h_basepoint, pok, self.nonce.as_ref()) {
+      return false;
+    }
+
+    self.reserve_commitment = match self.reserve_commitment {
+      None => Some(pedersen_com),
+      Some(acc) => PublicKey::from_combination(&self.secp_inst, vec![&acc, &pedersen_com]).ok(),
+    };
+    self.transcript.input(bytes);
+    self.num_records += 1;
+
+    true
+  }
+}
@@ -0,0 +1,64 @@
+use std::thread;
+
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::SecretKey;
+
+use crate::misc::single_base_product;
+use crate::ledger::{LedgerAccount, LedgerSnapshot};
+use crate::dlog::{DiscreteLogTable, decrypt_commitment};
+
+// A ledger account that belongs to one of the scanned keys
+#[derive (Clone)]
+pub struct ScanMatch {
+  pub index: usize,                           //position of the account in the snapshot
+  pub id: usize,                              //ledger id of the account
+  pub key: SecretKey,                         //k with pubkey.y == pubkey.x^k
+  pub amount: Option<u64>,                    //decrypted balance, None if above the table bound
+}
+
+//first key that owns account, if any
+fn match_account(secp_inst: &Secp256k1, account: &LedgerAccount, keys: &[SecretKey]) -> Option<SecretKey> {
+  keys.iter()
+    .find(|key| single_base_product(secp_inst, account.pubkey.x, (*key).clone()) == account.pubkey.y)
+    .cloned()
+}
+
+//scans accounts, which start at position offset in the snapshot
+fn scan_accounts(accounts: &[LedgerAccount], offset: usize, keys: &[SecretKey], table: &DiscreteLogTable) -> Vec<ScanMatch> {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+  accounts.iter().enumerate().filter_map(|(i, account)| {
+    match_account(&secp_inst, account, keys).map(|key| ScanMatch {
+      index: offset + i,
+      id: account.id,
+      amount: decrypt_commitment(&account.commitment, &key, table),
+      key,
+    })
+  }).collect()
+}
+
+// Finds the accounts of snapshot owned by any of keys, in snapshot order.
+// Costs one exponentiation per (account, key) pair until a key matches.
+pub fn scan(snapshot: &LedgerSnapshot, keys: &[SecretKey], table: &DiscreteLogTable) -> Vec<ScanMatch> {
+  scan_accounts(&snapshot.accounts, 0, keys, table)
+}
+
+// As scan, but splits the snapshot into num_threads contiguous chunks that are
+// scanned concurrently. The result is the same as that of scan.
+pub fn scan_parallel(snapshot: &LedgerSnapshot, keys: &[SecretKey], table: &DiscreteLogTable, num_threads: usize) -> Vec<ScanMatch> {
+  assert!(num_threads != 0);
+
+  if snapshot.accounts.is_empty() {
+    return Vec::new();
+  }
+  let chunk_size = snapshot.accounts.len().div_ceil(num_threads);
+
+  thread::scope(|s| {
+    let handles: Vec<_> = snapshot.accounts.chunks(chunk_size).enumerate()
+      .map(|(c, chunk)| s.spawn(move || scan_accounts(chunk, c * chunk_size, keys, table)))
+      .collect();
+
+    handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+  })
+}
@@ -1,3 +1,5 @@
+use std::io::{self, Read, Write};
+
 use rand::{thread_rng, Rng};
 use secp256k1zkp as secp;
 use secp::Secp256k1;
@@ -8,8 +10,22 @@ use crate::misc::GENERATOR_G;
 use crate::misc::GENERATOR_H;
 use crate::misc::MAX_AMOUNT_PER_OUTPUT;
 use crate::misc::amount_to_key;
+use crate::misc::POINT_SIZE;
+use crate::misc::{read_point, read_record_or_eof, write_point};
+use crate::misc::pedersen_commitment;
+use crate::misc::Nonce;
+use crate::ledger::{Ledger, LedgerSnapshot};
+use crate::dlog::{DiscreteLogTable, decrypt_commitment};
+use crate::nummatus_exchange::LedgerVerifyError;
+use crate::scan::{ScanMatch, scan_parallel};
+use crate::keychain::Keychain;
+use crate::interactive::SimpleProver;
 
 use crate::simple_nizk::SimplePoK;
+use crate::simple_nizk::SIMPLE_POK_SIZE;
+
+/// Size of one (pubkey, commitment, pederson, pok) record on the wire
+pub const SIMPLE_RECORD_SIZE: usize = 5 * POINT_SIZE + SIMPLE_POK_SIZE;
 
 pub struct Simple {
   pub pubkey_list: Vec<QPublicKey>,           //Quisquis PublicKey
@@ -36,26 +52,76 @@ impl Simple {
   }
 
   pub fn verify(&self) -> bool {
+    self.verify_with_nonce(None)
+  }
 
-    assert!(self.commitment_list.len() == self.pubkey_list.len());
-    assert!(self.commitment_list.len() == self.pederson_list.len());
-    assert!(self.commitment_list.len() == self.pok_list.len());
-    assert!(self.commitment_list.len() != 0);
+  // Verifies a proof made for the audit with this nonce
+  pub fn verify_with_nonce(&self, nonce: Option<&Nonce>) -> bool {
+
+    // An empty proof, or one whose lists do not line up, never verifies
+    let len = self.commitment_list.len();
+    if len == 0 || self.pubkey_list.len() != len || self.pederson_list.len() != len || self.pok_list.len() != len {
+      return false;
+    }
 
-    for i in 0..self.commitment_list.len() {
-      if SimplePoK::verify_pok(
+    for i in 0..len {
+      if !SimplePoK::verify_pok(
         self.pubkey_list[i],
         self.commitment_list[i],
         self.pederson_list[i],
         self.h_basepoint,
         self.pok_list[i].clone(),
-      ) == false {
+        nonce,
+      ) {
         return false;
       }
     }
 
     true
   }
+
+  pub fn h_basepoint(&self) -> PublicKey {
+    self.h_basepoint
+  }
+
+  // Writes h followed by (pubkey, commitment, pederson, pok) for every output
+  pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+    write_point(&secp_inst, writer, &self.h_basepoint)?;
+    for i in 0..self.pubkey_list.len() {
+      self.pubkey_list[i].write_to(&secp_inst, writer)?;
+      self.commitment_list[i].write_to(&secp_inst, writer)?;
+      write_point(&secp_inst, writer, &self.pederson_list[i])?;
+      self.pok_list[i].write_to(writer)?;
+    }
+    Ok(())
+  }
+
+  // Reads a proof written by write_to, one fixed-size record at a time up to
+  // the end of the stream. Every point and scalar must be a valid encoding, and a proof
+  // without records or ending in a partial record is rejected.
+  pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Simple> {
+    let secp_inst = Secp256k1::without_caps();
+
+    let mut proof = Simple::new(0);
+    proof.g_basepoint = PublicKey::from_slice(&secp_inst, &GENERATOR_G).unwrap();
+    proof.h_basepoint = read_point(&secp_inst, reader)?;
+
+    let mut buf = [0u8; SIMPLE_RECORD_SIZE];
+    while read_record_or_eof(reader, &mut buf)? {
+      let mut record = &buf[..];
+      proof.pubkey_list.push(QPublicKey::read_from(&secp_inst, &mut record)?);
+      proof.commitment_list.push(QPublicKey::read_from(&secp_inst, &mut record)?);
+      proof.pederson_list.push(read_point(&secp_inst, &mut record)?);
+      proof.pok_list.push(SimplePoK::read_from(&secp_inst, &mut record)?);
+    }
+
+    if proof.pok_list.is_empty() {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "Simple proof without records"));
+    }
+    Ok(proof)
+  }
 }
 
 pub struct SimpleExchange {
@@ -90,19 +156,19 @@ impl SimpleExchange {
 
         simproof.pubkey_list[i].x = PublicKey::from_slice(&secp_inst, &GENERATOR_G).unwrap();   //generating PublicKey from SecretKey
         simproof.pubkey_list[i].x.mul_assign(&secp_inst, &r1).unwrap();
-        simproof.pubkey_list[i].y = simproof.pubkey_list[i].x.clone();
+        simproof.pubkey_list[i].y = simproof.pubkey_list[i].x;
         simproof.pubkey_list[i].y.mul_assign(&secp_inst, &okeys[i]).unwrap();
 
 
-        simproof.commitment_list[i].x = simproof.pubkey_list[i].x.clone();        //generating commitment from PublicKey and amount
+        simproof.commitment_list[i].x = simproof.pubkey_list[i].x;        //generating commitment from PublicKey and amount
         simproof.commitment_list[i].x.mul_assign(&secp_inst, &r2).unwrap();
-        let mut v_g = simproof.g_basepoint.clone();
+        let mut v_g = simproof.g_basepoint;
         v_g.mul_assign(&secp_inst, &amount_to_key(&secp_inst, amounts[i])).unwrap();
-        let mut r2_d = simproof.pubkey_list[i].y.clone();
+        let mut r2_d = simproof.pubkey_list[i].y;
         r2_d.mul_assign(&secp_inst, &r2).unwrap();
         simproof.commitment_list[i].y = PublicKey::from_combination(&secp_inst, vec![&v_g, &r2_d]).unwrap();
 
-        let mut k_h = simproof.h_basepoint.clone();                              //generating Pedersen commitment from blinding factor
+        let mut k_h = simproof.h_basepoint;                              //generating Pedersen commitment from blinding factor
         k_h.mul_assign(&secp_inst, &okeys[i].clone()).unwrap();
         simproof.pederson_list[i] = PublicKey::from_combination(&secp_inst, vec![&v_g, &k_h]).unwrap();
     }
@@ -114,7 +180,113 @@ impl SimpleExchange {
     }
   }
 
+  // Reveals the accounts of owner alive at the given height of the ledger,
+  // decrypting their amounts with table. Fails with AmountsOutOfRange if an
+  // amount is above the table bound.
+  pub fn from_ledger(ledger: &Ledger, owner: usize, height: u64, table: &DiscreteLogTable) -> Result<SimpleExchange, LedgerVerifyError> {
+
+    let owned = ledger.owned_accounts(owner, height);
+    let olist_size = owned.len();
+    let mut simproof = Simple::new(olist_size);
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut okeys = Vec::new();
+
+    simproof.g_basepoint = PublicKey::from_slice(&secp_inst, &GENERATOR_G).unwrap();
+    simproof.h_basepoint = PublicKey::from_slice(&secp_inst, &GENERATOR_H).unwrap();
+
+    let mut out_of_range = Vec::new();
+
+    for (i, &id) in owned.iter().enumerate() {
+      let account = ledger.account(id);
+      okeys.push(ledger.key(id));
+
+      let amount = match decrypt_commitment(&account.commitment, &okeys[i], table) {
+        Some(amount) => amount,
+        None => {
+          out_of_range.push(i);
+          continue;
+        },
+      };
+
+      simproof.pubkey_list[i] = account.pubkey;
+      simproof.commitment_list[i] = account.commitment;
+      simproof.pederson_list[i] = pedersen_commitment(&secp_inst, simproof.g_basepoint, simproof.h_basepoint,
+                                    amount, okeys[i].clone());
+    }
+
+    if !out_of_range.is_empty() {
+      return Err(LedgerVerifyError::AmountsOutOfRange { indices: out_of_range });
+    }
+    Ok(SimpleExchange  {
+      own_list_size: olist_size,
+      simple_proof: simproof,
+      own_keys: okeys,
+    })
+  }
+
+  // Builds an exchange from the accounts of snapshot owned by keys, found by
+  // scanning the snapshot with num_threads threads
+  pub fn from_keys(snapshot: &LedgerSnapshot, keys: &[SecretKey], table: &DiscreteLogTable, num_threads: usize) -> Result<SimpleExchange, LedgerVerifyError> {
+    SimpleExchange::from_scan(snapshot, scan_parallel(snapshot, keys, table, num_threads))
+  }
+
+  // As from_keys, with the keys regenerated from the exchange's keychain
+  pub fn from_keychain(
+    snapshot: &LedgerSnapshot,
+    keychain: &Keychain,
+    table: &DiscreteLogTable,
+    gap_limit: u32,
+    num_threads: usize,
+    ) -> Result<SimpleExchange, LedgerVerifyError> {
+    SimpleExchange::from_scan(snapshot, keychain.scan(snapshot, table, gap_limit, num_threads))
+  }
+
+  fn from_scan(snapshot: &LedgerSnapshot, matches: Vec<ScanMatch>) -> Result<SimpleExchange, LedgerVerifyError> {
+
+    let olist_size = matches.len();
+    let mut simproof = Simple::new(olist_size);
+    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+    let mut okeys = Vec::new();
+
+    simproof.g_basepoint = PublicKey::from_slice(&secp_inst, &GENERATOR_G).unwrap();
+    simproof.h_basepoint = PublicKey::from_slice(&secp_inst, &GENERATOR_H).unwrap();
+
+    let out_of_range: Vec<usize> = (0..olist_size).filter(|&i| matches[i].amount.is_none()).collect();
+    if !out_of_range.is_empty() {
+      return Err(LedgerVerifyError::AmountsOutOfRange { indices: out_of_range });
+    }
+
+    for (i, found) in matches.into_iter().enumerate() {
+      let account = snapshot.accounts[found.index];
+      let amount = found.amount.unwrap();
+
+      simproof.pubkey_list[i] = account.pubkey;
+      simproof.commitment_list[i] = account.commitment;
+      simproof.pederson_list[i] = pedersen_commitment(&secp_inst, simproof.g_basepoint, simproof.h_basepoint,
+                                    amount, found.key.clone());
+      okeys.push(found.key);
+    }
+
+    Ok(SimpleExchange  {
+      own_list_size: olist_size,
+      simple_proof: simproof,
+      own_keys: okeys,
+    })
+  }
+
+  // Decrypts the commitment of every owned output; None if it exceeds the table bound
+  pub fn decrypt_amounts(&self, table: &DiscreteLogTable) -> Vec<Option<u64>> {
+    (0..self.own_list_size)
+      .map(|i| decrypt_commitment(&self.simple_proof.commitment_list[i], &self.own_keys[i], table))
+      .collect()
+  }
+
   pub fn generate_proof(&mut self) -> Simple {
+    self.generate_proof_with_nonce(None)
+  } // end generate_proof
+
+  // Proof for the audit with this nonce, which only verifies with the same nonce
+  pub fn generate_proof_with_nonce(&mut self, nonce: Option<&Nonce>) -> Simple {
 
     for i in 0..self.own_list_size {
 
@@ -123,7 +295,8 @@ impl SimpleExchange {
                                             self.simple_proof.commitment_list[i],
                                             self.simple_proof.pederson_list[i],
                                             self.own_keys[i].clone(),
-                                            self.simple_proof.h_basepoint,     
+                                            self.simple_proof.h_basepoint,
+                                            nonce,
                                           );
       } 
 
@@ -135,6 +308,17 @@ impl SimpleExchange {
       g_basepoint: self.simple_proof.g_basepoint,
       h_basepoint: self.simple_proof.h_basepoint,
     }
-  } // end generate_proof
+  } // end generate_proof_with_nonce
+
+  // Prover for an interactive session over the exchange's owned accounts
+  pub fn interactive_prover(&self) -> SimpleProver {
+    SimpleProver::new(
+      self.simple_proof.h_basepoint,
+      self.simple_proof.pubkey_list.clone(),
+      self.simple_proof.commitment_list.clone(),
+      self.simple_proof.pederson_list.clone(),
+      self.own_keys.clone(),
+    )
+  } // end interactive_prover
 
 } // end Simplus implementation 
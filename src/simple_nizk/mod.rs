@@ -1,14 +1,20 @@
-use rand::thread_rng;
+use std::io::{self, Read, Write};
+
+use rand::{thread_rng, Rng};
 use secp256k1zkp as secp;
 use secp::Secp256k1;
 use secp::key::{SecretKey, PublicKey, ZERO_KEY};
 
 use crate::misc::QPublicKey;
 use crate::misc::single_base_product;
-use crate::misc::double_base_product;
 use crate::misc::ratio;
+use crate::misc::{checked_double_base_product, checked_ratio};
+use crate::misc::{is_valid_point, is_valid_scalar};
 use crate::misc::hash_simple_tx;
 use crate::misc::a_minus_bx;
+use crate::misc::{write_scalar, read_scalar};
+use crate::misc::SCALAR_SIZE;
+use crate::misc::Nonce;
 
 #[derive (Clone)]
 pub struct SimplePoK {
@@ -16,8 +22,13 @@ pub struct SimplePoK {
 	s : SecretKey,
 }
 
+/// Size of a serialized SimplePoK
+pub const SIMPLE_POK_SIZE: usize = 2 * SCALAR_SIZE;
+
 impl SimplePoK {
 
+	// All-zero placeholder that verify_pok rejects, like QPublicKey::new
+	#[allow(clippy::new_without_default)]
 	pub fn new() -> SimplePoK {
 		SimplePoK {
 			e : ZERO_KEY,
@@ -31,36 +42,56 @@ impl SimplePoK {
 		pederson : PublicKey,
 		alpha : SecretKey,
 		h_j : PublicKey,
+		nonce : Option<&Nonce>,
 		) -> SimplePoK {
 
 		let mut rng = thread_rng();
 	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+	    let r1 = SecretKey::new(&secp_inst, &mut rng);
+
+	    SimplePoK::create_pok_with_nonces(&secp_inst, pubkey, commitment, pederson, alpha, h_j, r1, nonce)
+	}
+
+	// As create_pok_from_representation with the prover's randomness r1
+	// given explicitly, for known-answer tests. Never reuse r1 across proofs:
+	// two PoKs with the same r1 reveal alpha.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_pok_with_nonces (
+		secp_inst : &Secp256k1,
+		pubkey : QPublicKey,
+		commitment : QPublicKey,
+		pederson : PublicKey,
+		alpha : SecretKey,
+		h_j : PublicKey,
+		r1 : SecretKey,
+		nonce : Option<&Nonce>,
+		) -> SimplePoK {
 
 	    let mut rpok = SimplePoK::new();
-	    let r1 = SecretKey::new(&secp_inst, &mut rng);
 
 	    //v1 = a^r1
-	    let v1 = single_base_product(&secp_inst, pubkey.x.clone(), r1.clone());
+	    let v1 = single_base_product(secp_inst, pubkey.x, r1.clone());
 
 	    //v2 = (h*c^-1)^r1
-	    let h_minus_c = ratio(&secp_inst, h_j.clone(), commitment.x.clone());
-	    let v2 = single_base_product(&secp_inst, h_minus_c.clone(), r1.clone());
+	    let h_minus_c = ratio(secp_inst, h_j, commitment.x);
+	    let v2 = single_base_product(secp_inst, h_minus_c, r1.clone());
 
-	    let hash_scalar = hash_simple_tx(&secp_inst,
+	    let hash_scalar = hash_simple_tx(secp_inst,
 	    								h_j,
-	    								pubkey.x.clone(),          //a
-	    								pubkey.y.clone(),          //b
-	    								commitment.x.clone(),      //c
-	    								commitment.y.clone(),      //d
-	    								pederson.clone(),          //p
-	    								v1.clone(),
-	    								v2.clone()
+	    								pubkey.x,          //a
+	    								pubkey.y,          //b
+	    								commitment.x,      //c
+	    								commitment.y,      //d
+	    								pederson,          //p
+	    								v1,
+	    								v2,
+	    								nonce,
 	    								);
 
 	    // Calculation of e_1
 	    rpok.e = hash_scalar;                                      // e_1 = H(h_j, a, b, c, d, p, v1, v2)
 
-	    rpok.s = a_minus_bx(&secp_inst, r1.clone(), rpok.e.clone(), alpha);
+	    rpok.s = a_minus_bx(secp_inst, r1.clone(), rpok.e.clone(), alpha);
 
 	    rpok
 	}
@@ -71,31 +102,95 @@ impl SimplePoK {
 		pederson : PublicKey,
 		h_j : PublicKey,
 		rpok : SimplePoK,
+		nonce : Option<&Nonce>,
 		) -> bool {
 
 	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
 
-	    //v1 = a^s * b^e    
-	    let v1 = double_base_product(&secp_inst, pubkey.x.clone(), pubkey.y.clone(), rpok.s.clone(), rpok.e.clone());
+	    // Placeholder or off-curve points and out-of-range scalars never verify
+	    let points = [pubkey.x, pubkey.y, commitment.x, commitment.y, pederson, h_j];
+	    if !points.iter().all(|point| is_valid_point(&secp_inst, point)) || !is_valid_scalar(&secp_inst, &rpok.e) || !is_valid_scalar(&secp_inst, &rpok.s) {
+	    	return false;
+	    }
 
-	    //v2 = c^s * h^s2 * (d*p^-1)^e1  
-	    let p_minus_d = ratio(&secp_inst, pederson.clone(), commitment.y.clone());
-	    let h_minus_c = ratio(&secp_inst, h_j.clone(), commitment.x.clone());
-	    let v2 = double_base_product(&secp_inst, h_minus_c.clone(), p_minus_d.clone(), rpok.s.clone(), rpok.e.clone());
+	    // A degenerate statement or PoK whose first messages would be the identity never verifies
+	    let (v1, v2) = match recompute_first_message(&secp_inst, pubkey, commitment, pederson, h_j, &rpok.e, &rpok.s) {
+	    	Some(first_messages) => first_messages,
+	    	None => return false,
+	    };
 
 	    let hash_scalar = hash_simple_tx(&secp_inst,
 	    								h_j,
-	    								pubkey.x.clone(),          //a
-	    								pubkey.y.clone(),          //b
-	    								commitment.x.clone(),      //c
-	    								commitment.y.clone(),      //d
-	    								pederson.clone(),          //p
-	    								v1.clone(),
-	    								v2.clone()
+	    								pubkey.x,          //a
+	    								pubkey.y,          //b
+	    								commitment.x,      //c
+	    								commitment.y,      //d
+	    								pederson,          //p
+	    								v1,
+	    								v2,
+	    								nonce,
 	    								);
 
 	    let e = rpok.e.clone();
 
 	    e == hash_scalar    // comparing e from SimplePoK and evaluation of the scalar-hash
 	}
-}
\ No newline at end of file
+	//writes e and s in that order
+	pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+	    write_scalar(writer, &self.e)?;
+	    write_scalar(writer, &self.s)
+	}
+
+	//reads e and s in that order
+	pub fn read_from<R: Read>(secp_inst: &Secp256k1, reader: &mut R) -> io::Result<SimplePoK> {
+	    Ok(SimplePoK {
+	    	e : read_scalar(secp_inst, reader)?,
+	    	s : read_scalar(secp_inst, reader)?,
+	    })
+	}
+}
+
+// First messages (v1, v2) of one output and the randomness r1 behind them,
+// for when the challenge comes from an interactive verifier
+pub(crate) fn first_message<R: Rng> (
+	secp_inst : &Secp256k1,
+	rng : &mut R,
+	pubkey : QPublicKey,
+	commitment : QPublicKey,
+	h_j : PublicKey,
+	) -> ((PublicKey, PublicKey), SecretKey) {
+
+	let r1 = SecretKey::new(secp_inst, rng);
+
+	//v1 = a^r1
+	let v1 = single_base_product(secp_inst, pubkey.x, r1.clone());
+
+	//v2 = (h*c^-1)^r1
+	let h_minus_c = ratio(secp_inst, h_j, commitment.x);
+	let v2 = single_base_product(secp_inst, h_minus_c, r1.clone());
+
+	((v1, v2), r1)
+}
+
+// Recomputes the first messages (v1, v2) of one output from the challenge e
+// and response s, or None if any intermediate point is the identity
+pub(crate) fn recompute_first_message (
+	secp_inst : &Secp256k1,
+	pubkey : QPublicKey,
+	commitment : QPublicKey,
+	pederson : PublicKey,
+	h_j : PublicKey,
+	e : &SecretKey,
+	s : &SecretKey,
+	) -> Option<(PublicKey, PublicKey)> {
+
+	//v1 = a^s * b^e
+	let v1 = checked_double_base_product(secp_inst, pubkey.x, pubkey.y, s.clone(), e.clone())?;
+
+	//v2 = (h*c^-1)^s * (p*d^-1)^e
+	let p_minus_d = checked_ratio(secp_inst, pederson, commitment.y)?;
+	let h_minus_c = checked_ratio(secp_inst, h_j, commitment.x)?;
+	let v2 = checked_double_base_product(secp_inst, h_minus_c, p_minus_d, s.clone(), e.clone())?;
+
+	Some((v1, v2))
+}
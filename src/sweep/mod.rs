@@ -0,0 +1,246 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::misc::Nonce;
+use crate::nummatus_exchange::NummatusExchange;
+use crate::simple_exchange::SimpleExchange;
+
+// A list of sizes given on the command line either as "100,1000,10000"
+// or as an inclusive range with a step, "100:1000:100"
+#[derive(Debug, Clone)]
+pub struct SizeList(pub Vec<usize>);
+
+impl FromStr for SizeList {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<SizeList, String> {
+    let parse = |v: &str| v.trim().parse::<usize>().map_err(|e| format!("invalid size {:?}: {}", v, e));
+
+    let sizes = if s.contains(':') {
+      let parts: Vec<&str> = s.split(':').collect();
+      if parts.len() != 3 {
+        return Err(format!("expected start:end:step, got {:?}", s));
+      }
+      let (start, end, step) = (parse(parts[0])?, parse(parts[1])?, parse(parts[2])?);
+      if step == 0 {
+        return Err("step must be non-zero".to_string());
+      }
+      (start..=end).step_by(step).collect()
+    } else {
+      s.split(',').map(parse).collect::<Result<Vec<usize>, String>>()?
+    };
+
+    if sizes.is_empty() {
+      return Err(format!("no sizes in {:?}", s));
+    }
+    Ok(SizeList(sizes))
+  }
+}
+
+// Number of iterations given on the command line, which must be at least 1
+// since timings are averaged over them
+pub fn parse_num_iter(s: &str) -> Result<u32, String> {
+  match s.trim().parse::<u32>() {
+    Ok(0) => Err("number of iterations must be at least 1".to_string()),
+    Ok(num_iter) => Ok(num_iter),
+    Err(e) => Err(format!("invalid number of iterations {:?}: {}", s, e)),
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum OutputFormat {
+  Csv,
+  Json,
+}
+
+impl FromStr for OutputFormat {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<OutputFormat, String> {
+    match s {
+      "csv" => Ok(OutputFormat::Csv),
+      "json" => Ok(OutputFormat::Json),
+      _ => Err(format!("unknown output format {:?}, expected csv or json", s)),
+    }
+  }
+}
+
+// Timings for one protocol configuration, averaged over num_iter runs
+#[derive(Debug, Clone)]
+pub struct SweepRow {
+  pub protocol: &'static str,
+  pub anon_list_size: usize,
+  pub own_list_size: usize,
+  pub num_iter: u32,
+  pub setup_time: Duration,                   //time to build the exchange
+  pub gen_time: Duration,                     //average proof generation time
+  pub ver_time: Duration,                     //average proof verification time
+  pub proof_size: usize,                      //serialized proof size in bytes
+}
+
+// Counts the bytes written to it, to measure proof size without buffering
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.0 += buf.len();
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+// With compact set, proofs use the single global challenge layout of CompactNummatus.
+// Proofs are made and verified for the audit with nonce, if there is one.
+pub fn measure_nummatus(anon_list_size: usize, own_list_size: usize, num_iter: u32, compact: bool, nonce: Option<&Nonce>) -> SweepRow {
+  assert!(num_iter != 0);
+
+  let setup_start = Instant::now();
+  let mut n_exch = NummatusExchange::new(anon_list_size, own_list_size);
+  let setup_time = setup_start.elapsed();
+
+  let mut total_gen_proof_duration = Duration::new(0, 0);
+  let mut total_ver_proof_duration = Duration::new(0, 0);
+  let mut proof_size = 0;
+
+  for _i in 0..num_iter {
+    let mut counter = ByteCounter(0);
+
+    if compact {
+      let gen_proof_start = Instant::now();
+      let quisquis_proof = n_exch.generate_compact_proof_with_nonce(nonce);
+      total_gen_proof_duration += gen_proof_start.elapsed();
+
+      let ver_proof_start = Instant::now();
+      assert!(quisquis_proof.verify_with_nonce(nonce));
+      total_ver_proof_duration += ver_proof_start.elapsed();
+
+      quisquis_proof.write_to(&mut counter).unwrap();
+    } else {
+      let gen_proof_start = Instant::now();
+      let quisquis_proof = n_exch.generate_proof_with_nonce(nonce);
+      total_gen_proof_duration += gen_proof_start.elapsed();
+
+      let ver_proof_start = Instant::now();
+      assert!(quisquis_proof.verify_with_nonce(nonce));
+      total_ver_proof_duration += ver_proof_start.elapsed();
+
+      quisquis_proof.write_to(&mut counter).unwrap();
+    }
+    proof_size = counter.0;
+  }
+
+  SweepRow {
+    protocol: if compact { "nummatus-compact" } else { "nummatus" },
+    anon_list_size,
+    own_list_size,
+    num_iter,
+    setup_time,
+    gen_time: total_gen_proof_duration / num_iter,
+    ver_time: total_ver_proof_duration / num_iter,
+    proof_size,
+  }
+}
+
+// Simplus reveals the owned accounts, so its anonymity set is the owned list
+pub fn measure_simple(own_list_size: usize, num_iter: u32, nonce: Option<&Nonce>) -> SweepRow {
+  assert!(num_iter != 0);
+
+  let setup_start = Instant::now();
+  let mut quisquis_exch = SimpleExchange::new(own_list_size);
+  let setup_time = setup_start.elapsed();
+
+  let mut total_gen_proof_duration = Duration::new(0, 0);
+  let mut total_ver_proof_duration = Duration::new(0, 0);
+  let mut proof_size = 0;
+
+  for _i in 0..num_iter {
+    let gen_proof_start = Instant::now();
+    let simple_proof = quisquis_exch.generate_proof_with_nonce(nonce);
+    total_gen_proof_duration += gen_proof_start.elapsed();
+
+    let ver_proof_start = Instant::now();
+    assert!(simple_proof.verify_with_nonce(nonce));
+    total_ver_proof_duration += ver_proof_start.elapsed();
+
+    let mut counter = ByteCounter(0);
+    simple_proof.write_to(&mut counter).unwrap();
+    proof_size = counter.0;
+  }
+
+  SweepRow {
+    protocol: "simple",
+    anon_list_size: own_list_size,
+    own_list_size,
+    num_iter,
+    setup_time,
+    gen_time: total_gen_proof_duration / num_iter,
+    ver_time: total_ver_proof_duration / num_iter,
+    proof_size,
+  }
+}
+
+impl fmt::Display for SweepRow {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "Protocol = {}, anon_list_size = {}, own_list_size = {}, num_iter = {}",
+      self.protocol, self.anon_list_size, self.own_list_size, self.num_iter)?;
+    writeln!(f, "Setup time = {:?}", self.setup_time)?;
+    writeln!(f, "Average proof generation time = {:?}", self.gen_time)?;
+    writeln!(f, "Average proof verification time = {:?}", self.ver_time)?;
+    write!(f, "Proof size = {} bytes", self.proof_size)
+  }
+}
+
+// Writes one CSV line per row after a header; times are in seconds
+pub fn write_csv<W: Write>(writer: &mut W, rows: &[SweepRow]) -> io::Result<()> {
+  writeln!(writer, "protocol,anon_list_size,own_list_size,num_iter,setup_time_s,gen_time_s,ver_time_s,proof_size_bytes")?;
+  for row in rows {
+    writeln!(writer, "{},{},{},{},{:.9},{:.9},{:.9},{}",
+      row.protocol, row.anon_list_size, row.own_list_size, row.num_iter,
+      row.setup_time.as_secs_f64(), row.gen_time.as_secs_f64(), row.ver_time.as_secs_f64(),
+      row.proof_size)?;
+  }
+  Ok(())
+}
+
+// Writes the rows as a JSON array of objects with the same fields as the CSV
+pub fn write_json<W: Write>(writer: &mut W, rows: &[SweepRow]) -> io::Result<()> {
+  writeln!(writer, "[")?;
+  for (i, row) in rows.iter().enumerate() {
+    write!(writer, "  {{\"protocol\": \"{}\", \"anon_list_size\": {}, \"own_list_size\": {}, \"num_iter\": {}, \
+      \"setup_time_s\": {:.9}, \"gen_time_s\": {:.9}, \"ver_time_s\": {:.9}, \"proof_size_bytes\": {}}}",
+      row.protocol, row.anon_list_size, row.own_list_size, row.num_iter,
+      row.setup_time.as_secs_f64(), row.gen_time.as_secs_f64(), row.ver_time.as_secs_f64(),
+      row.proof_size)?;
+    writeln!(writer, "{}", if i + 1 < rows.len() { "," } else { "" })?;
+  }
+  writeln!(writer, "]")
+}
+
+pub fn write_rows<W: Write>(writer: &mut W, format: OutputFormat, rows: &[SweepRow]) -> io::Result<()> {
+  match format {
+    OutputFormat::Csv => write_csv(writer, rows),
+    OutputFormat::Json => write_json(writer, rows),
+  }
+}
+
+// The binaries took a single configuration as positional arguments before
+// they had subcommands. Arguments that do not start with one of subcommands
+// or a help or version flag get "run" inserted in front of them, so that
+// "nummatus 100 25 -n 100" keeps working as "nummatus run 100 25 -n 100".
+pub fn with_default_subcommand<I: IntoIterator<Item = String>>(args: I, subcommands: &[&str]) -> Vec<String> {
+  let mut args: Vec<String> = args.into_iter().collect();
+  let keep = match args.get(1) {
+    None => true,
+    Some(first) => subcommands.contains(&first.as_str())
+                     || ["help", "-h", "--help", "-V", "--version"].contains(&first.as_str()),
+  };
+  if !keep {
+    args.insert(1, "run".to_string());
+  }
+  args
+}
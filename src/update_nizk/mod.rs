@@ -0,0 +1,239 @@
+use rand::thread_rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey, ZERO_KEY};
+
+use crate::misc::QPublicKey;
+use crate::misc::single_base_product;
+use crate::misc::double_base_product;
+use crate::misc::{checked_double_base_product, checked_triple_base_product, checked_ratio};
+use crate::misc::{is_valid_point, is_valid_scalar};
+use crate::misc::hash_points;
+use crate::misc::a_minus_bx;
+use crate::misc::signed_amount_to_key;
+
+// Domain tags of the challenges of the two update proofs
+const RERANDOMISE_DOMAIN: &[u8] = b"quisquis/rerandomise/challenge";
+const BALANCE_UPDATE_DOMAIN: &[u8] = b"quisquis/balance-update/challenge";
+
+// Proof that a Quisquis public key (x', y') is a re-randomisation (x^r, y^r)
+// of (x, y), i.e. a proof of equality of discrete logs of x' to base x and of
+// y' to base y, so the account keeps the same secret key.
+#[derive (Clone)]
+pub struct RerandomisePoK {
+	pub e : SecretKey,
+	pub s : SecretKey,
+}
+
+impl RerandomisePoK {
+
+	// All-zero placeholder, filled in by create_pok
+	#[allow(clippy::new_without_default)]
+	pub fn new() -> RerandomisePoK {
+		RerandomisePoK {
+			e : ZERO_KEY,
+			s : ZERO_KEY,
+		}
+	}
+
+	pub fn create_pok (
+		pubkey : QPublicKey,
+		new_pubkey : QPublicKey,
+		r : SecretKey,
+		) -> RerandomisePoK {
+
+		let mut rng = thread_rng();
+	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+	    let mut rpok = RerandomisePoK::new();
+	    let w = SecretKey::new(&secp_inst, &mut rng);
+
+	    //u1 = x^w
+	    let u1 = single_base_product(&secp_inst, pubkey.x, w.clone());
+
+	    //u2 = y^w
+	    let u2 = single_base_product(&secp_inst, pubkey.y, w.clone());
+
+	    rpok.e = hash_points(&secp_inst, RERANDOMISE_DOMAIN, &[pubkey.x, pubkey.y, new_pubkey.x, new_pubkey.y, u1, u2], None);
+	    rpok.s = a_minus_bx(&secp_inst, w, rpok.e.clone(), r);
+	    rpok
+	}
+
+	pub fn verify_pok (
+		pubkey : QPublicKey,
+		new_pubkey : QPublicKey,
+		rpok : RerandomisePoK,
+		) -> bool {
+
+	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+	    // Placeholder or off-curve points and out-of-range scalars never verify
+	    let points = [pubkey.x, pubkey.y, new_pubkey.x, new_pubkey.y];
+	    if !points.iter().all(|point| is_valid_point(&secp_inst, point)) || !is_valid_scalar(&secp_inst, &rpok.e) || !is_valid_scalar(&secp_inst, &rpok.s) {
+	    	return false;
+	    }
+
+	    //u1 = x^s * x'^e
+	    let u1 = match checked_double_base_product(&secp_inst, pubkey.x, new_pubkey.x, rpok.s.clone(), rpok.e.clone()) {
+	    	Some(u1) => u1,
+	    	None => return false,
+	    };
+
+	    //u2 = y^s * y'^e
+	    let u2 = match checked_double_base_product(&secp_inst, pubkey.y, new_pubkey.y, rpok.s.clone(), rpok.e.clone()) {
+	    	Some(u2) => u2,
+	    	None => return false,
+	    };
+
+	    let hash_scalar = hash_points(&secp_inst, RERANDOMISE_DOMAIN, &[pubkey.x, pubkey.y, new_pubkey.x, new_pubkey.y, u1, u2], None);
+
+	    rpok.e == hash_scalar
+	}
+}
+
+// Proof that a commitment (c', d') was obtained from (c, d) under public key
+// (a, b) as (c * a^t, d * g^delta * b^t) for some t and delta known to the
+// prover. It does not bound delta, so it shows the update is well formed but
+// not that the new balance is in range.
+#[derive (Clone)]
+pub struct BalanceUpdatePoK {
+	pub e : SecretKey,
+	pub s_t : SecretKey,
+	pub s_v : SecretKey,
+}
+
+impl BalanceUpdatePoK {
+
+	// All-zero placeholder, filled in by create_pok
+	#[allow(clippy::new_without_default)]
+	pub fn new() -> BalanceUpdatePoK {
+		BalanceUpdatePoK {
+			e : ZERO_KEY,
+			s_t : ZERO_KEY,
+			s_v : ZERO_KEY,
+		}
+	}
+
+	pub fn create_pok (
+		pubkey : QPublicKey,
+		commitment : QPublicKey,
+		new_commitment : QPublicKey,
+		g : PublicKey,
+		delta : i64,
+		t : SecretKey,
+		) -> BalanceUpdatePoK {
+
+		let mut rng = thread_rng();
+	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+	    let mut rpok = BalanceUpdatePoK::new();
+	    let w_t = SecretKey::new(&secp_inst, &mut rng);
+	    let w_v = SecretKey::new(&secp_inst, &mut rng);
+
+	    //u1 = a^w_t
+	    let u1 = single_base_product(&secp_inst, pubkey.x, w_t.clone());
+
+	    //u2 = g^w_v * b^w_t
+	    let u2 = double_base_product(&secp_inst, g, pubkey.y, w_v.clone(), w_t.clone());
+
+	    rpok.e = hash_points(&secp_inst, BALANCE_UPDATE_DOMAIN, &[g, pubkey.x, pubkey.y, commitment.x, commitment.y,
+	    									new_commitment.x, new_commitment.y, u1, u2], None);
+	    rpok.s_t = a_minus_bx(&secp_inst, w_t, rpok.e.clone(), t);
+	    rpok.s_v = if delta != 0 {
+	    	a_minus_bx(&secp_inst, w_v, rpok.e.clone(), signed_amount_to_key(&secp_inst, delta))
+	    } else {
+	    	w_v                                                     // s_v = w_v - e*0
+	    };
+	    rpok
+	}
+
+	pub fn verify_pok (
+		pubkey : QPublicKey,
+		commitment : QPublicKey,
+		new_commitment : QPublicKey,
+		g : PublicKey,
+		rpok : BalanceUpdatePoK,
+		) -> bool {
+
+	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+	    // Placeholder or off-curve points and out-of-range scalars never verify
+	    let points = [g, pubkey.x, pubkey.y, commitment.x, commitment.y, new_commitment.x, new_commitment.y];
+	    let scalars = [&rpok.e, &rpok.s_t, &rpok.s_v];
+	    if !points.iter().all(|point| is_valid_point(&secp_inst, point)) || !scalars.iter().all(|scalar| is_valid_scalar(&secp_inst, scalar)) {
+	    	return false;
+	    }
+
+	    // An unchanged commitment makes a ratio the identity, and never verifies
+	    let (c_ratio, d_ratio) = match (checked_ratio(&secp_inst, new_commitment.x, commitment.x),
+	    								checked_ratio(&secp_inst, new_commitment.y, commitment.y)) {
+	    	(Some(c_ratio), Some(d_ratio)) => (c_ratio, d_ratio),
+	    	_ => return false,
+	    };
+
+	    //u1 = a^s_t * (c'*c^-1)^e
+	    let u1 = match checked_double_base_product(&secp_inst, pubkey.x, c_ratio, rpok.s_t.clone(), rpok.e.clone()) {
+	    	Some(u1) => u1,
+	    	None => return false,
+	    };
+
+	    //u2 = g^s_v * b^s_t * (d'*d^-1)^e
+	    let u2 = match checked_triple_base_product(&secp_inst, g, pubkey.y, d_ratio,
+	    								rpok.s_v.clone(), rpok.s_t.clone(), rpok.e.clone()) {
+	    	Some(u2) => u2,
+	    	None => return false,
+	    };
+
+	    let hash_scalar = hash_points(&secp_inst, BALANCE_UPDATE_DOMAIN, &[g, pubkey.x, pubkey.y, commitment.x, commitment.y,
+	    									new_commitment.x, new_commitment.y, u1, u2], None);
+
+	    rpok.e == hash_scalar
+	}
+}
+
+// A Quisquis account update: the public key is re-randomised and the
+// commitment is re-randomised and has delta added to its amount, with a proof
+// of each so anyone can check the new account belongs to the same key.
+#[derive (Clone)]
+pub struct AccountUpdate {
+	pub pubkey : QPublicKey,
+	pub commitment : QPublicKey,
+	pub new_pubkey : QPublicKey,
+	pub new_commitment : QPublicKey,
+	pub rerandomise_pok : RerandomisePoK,
+	pub balance_pok : BalanceUpdatePoK,
+}
+
+impl AccountUpdate {
+
+	pub fn create (
+		pubkey : QPublicKey,
+		commitment : QPublicKey,
+		g : PublicKey,
+		delta : i64,
+		) -> AccountUpdate {
+
+		let mut rng = thread_rng();
+	    let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+	    let r = SecretKey::new(&secp_inst, &mut rng);
+	    let t = SecretKey::new(&secp_inst, &mut rng);
+
+	    let new_pubkey = pubkey.rerandomise(&secp_inst, &r);
+	    let new_commitment = commitment.update_commitment(&secp_inst, &pubkey, g, delta, &t);
+
+	    AccountUpdate {
+	    	pubkey,
+	    	commitment,
+	    	new_pubkey,
+	    	new_commitment,
+	    	rerandomise_pok : RerandomisePoK::create_pok(pubkey, new_pubkey, r),
+	    	balance_pok : BalanceUpdatePoK::create_pok(pubkey, commitment, new_commitment, g, delta, t),
+	    }
+	}
+
+	pub fn verify (&self, g : PublicKey) -> bool {
+	    RerandomisePoK::verify_pok(self.pubkey, self.new_pubkey, self.rerandomise_pok.clone()) &&
+	    BalanceUpdatePoK::verify_pok(self.pubkey, self.commitment, self.new_commitment, g, self.balance_pok.clone())
+	}
+}
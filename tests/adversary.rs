@@ -0,0 +1,98 @@
+use rand::thread_rng;
+use secp256k1zkp::key::{PublicKey, ZERO_KEY};
+
+use quisquis::adversary::{Attack, forge_nummatus, forge_simple};
+use quisquis::dlog::DiscreteLogTable;
+use quisquis::interactive::{
+  InteractiveProver, InteractiveVerifier, NummatusCommitment, NummatusVerifier, ProtocolError, SimpleResponse, SimpleVerifier,
+};
+use quisquis::ledger::Ledger;
+use quisquis::misc::{MAX_AMOUNT_PER_OUTPUT, QPublicKey};
+use quisquis::nummatus_exchange::{Nummatus, NummatusExchange};
+use quisquis::simple_exchange::{Simple, SimpleExchange};
+
+const NUM_ACCOUNTS: usize = 6;
+
+#[test]
+fn honest_proofs_are_accepted() {
+  let mut rng = thread_rng();
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT);
+  let ledger = Ledger::simulate(&mut rng, 1, 1, NUM_ACCOUNTS);
+  let snapshot = ledger.snapshot(ledger.height());
+
+  let nummatus = NummatusExchange::from_ledger(&ledger, 0, ledger.height(), &table).unwrap().generate_proof();
+  assert!(nummatus.verify_against_ledger(&snapshot, None).is_ok());
+
+  let simple = SimpleExchange::from_ledger(&ledger, 0, ledger.height(), &table).unwrap().generate_proof();
+  assert!(simple.verify());
+}
+
+#[test]
+fn nummatus_forgeries_are_rejected() {
+  let mut rng = thread_rng();
+  for &attack in Attack::ALL.iter() {
+    let forgery = forge_nummatus(&mut rng, attack, NUM_ACCOUNTS);
+    assert!(forgery.is_rejected(), "{} was accepted", attack.name());
+  }
+}
+
+#[test]
+fn simple_forgeries_are_rejected() {
+  let mut rng = thread_rng();
+  for &attack in Attack::ALL.iter() {
+    if let Some(forgery) = forge_simple(&mut rng, attack, NUM_ACCOUNTS) {
+      assert!(forgery.is_rejected(), "{} was accepted", attack.name());
+    }
+  }
+}
+
+#[test]
+fn stale_proof_is_rejected_for_unknown_entries() {
+  let mut rng = thread_rng();
+  let forgery = forge_nummatus(&mut rng, Attack::StaleHeight, NUM_ACCOUNTS);
+  assert!(forgery.proof.verify());
+  assert!(forgery.proof.verify_against_ledger(&forgery.snapshot, None).is_err());
+}
+
+// Proofs straight from ::new(), empty ones, honest ones with a placeholder
+// entry appended and interactive sessions over placeholders are rejected
+// without touching the curve arithmetic, which aborts on a zeroed point
+#[test]
+fn placeholder_proofs_are_rejected() {
+  assert!(!Nummatus::new(NUM_ACCOUNTS).verify());
+  assert!(!Nummatus::new(0).verify());
+  assert!(!Simple::new(NUM_ACCOUNTS).verify());
+  assert!(!Simple::new(0).verify());
+
+  let placeholder = Nummatus::new(1);
+  let mut nummatus = NummatusExchange::new(NUM_ACCOUNTS, 1).generate_proof();
+  nummatus.pubkey_list.push(placeholder.pubkey_list[0]);
+  nummatus.commitment_list.push(placeholder.commitment_list[0]);
+  nummatus.pedersen_com_list.push(placeholder.pedersen_com_list[0]);
+  nummatus.pok_list.push(placeholder.pok_list[0].clone());
+  assert!(!nummatus.verify());
+
+  let placeholder = Simple::new(1);
+  let mut simple = SimpleExchange::new(NUM_ACCOUNTS).generate_proof();
+  simple.pubkey_list.push(placeholder.pubkey_list[0]);
+  simple.commitment_list.push(placeholder.commitment_list[0]);
+  simple.pederson_list.push(placeholder.pederson_list[0]);
+  simple.pok_list.push(placeholder.pok_list[0].clone());
+  assert!(!simple.verify());
+
+  // The interactive verifiers refuse placeholder statements and zero responses
+  let (zero, blank) = (PublicKey::new(), QPublicKey::new());
+  let commitment = NummatusCommitment {
+    h_basepoint: zero,
+    pubkey_list: vec![blank],
+    commitment_list: vec![blank],
+    pedersen_com_list: vec![zero],
+    first_messages: vec![(zero, zero, zero)],
+  };
+  assert!(matches!(NummatusVerifier::new().challenge(commitment), Err(ProtocolError::InvalidPoint)));
+
+  let mut prover = SimpleExchange::new(NUM_ACCOUNTS).interactive_prover();
+  let mut verifier = SimpleVerifier::new();
+  verifier.challenge(prover.commit().unwrap()).unwrap();
+  assert!(!verifier.verify(SimpleResponse { responses: vec![ZERO_KEY; NUM_ACCOUNTS] }).unwrap());
+}
@@ -0,0 +1,121 @@
+use rand::thread_rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::SecretKey;
+
+use quisquis::anonymity::{AnonymitySetSelector, WholeLedger, UniformSampling, AgeMatched, BalanceBucketMatched, ledger_coverage};
+use quisquis::dlog::DiscreteLogTable;
+use quisquis::ledger::Ledger;
+use quisquis::misc::MAX_AMOUNT_PER_OUTPUT;
+use quisquis::nummatus_exchange::NummatusExchange;
+
+const EXCHANGE: usize = 0;
+const OTHER: usize = 1;
+const NUM_BLOCKS: u64 = 5;
+
+// Two foreign accounts in every block, with balances 10 * height and
+// 10 * height + 5, and one owned account with balance 33 in block 3.
+// Block 1 also has a foreign account that is spent in the last block.
+fn ledger() -> (Ledger, usize, usize) {
+  let mut rng = thread_rng();
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let key = || SecretKey::new(&secp_inst, &mut thread_rng());
+  let mut ledger = Ledger::new();
+  let mut owned = 0;
+  let mut spent = 0;
+
+  for height in 1..=NUM_BLOCKS {
+    ledger.advance();
+    for &balance in &[10 * height, 10 * height + 5] {
+      ledger.create_account(&mut rng, OTHER, key(), balance);
+    }
+    if height == 1 {
+      spent = ledger.create_account(&mut rng, OTHER, key(), 1);
+    }
+    if height == 3 {
+      owned = ledger.create_account(&mut rng, EXCHANGE, key(), 33);
+    }
+  }
+  ledger.spend(spent);
+  (ledger, owned, spent)
+}
+
+fn live(ledger: &Ledger) -> Vec<usize> {
+  ledger.snapshot(ledger.height()).accounts.iter().map(|account| account.id).collect()
+}
+
+fn select(selector: &dyn AnonymitySetSelector, ledger: &Ledger, owned: &[usize]) -> Vec<usize> {
+  let set = selector.select(ledger, ledger.height(), owned, &mut thread_rng());
+  assert!(set.windows(2).all(|pair| pair[0] < pair[1]), "{} set is not sorted", selector.name());
+  assert!(owned.iter().all(|id| set.contains(id)), "{} set misses an owned account", selector.name());
+  assert!(set.iter().all(|&id| ledger.is_live(id, ledger.height())), "{} set has a spent account", selector.name());
+  set
+}
+
+#[test]
+fn whole_ledger_covers_every_live_account() {
+  let (ledger, owned, spent) = ledger();
+  let set = select(&WholeLedger, &ledger, &[owned]);
+  assert_eq!(set, live(&ledger));
+  assert!(!set.contains(&spent));
+  assert_eq!(ledger_coverage(&ledger, ledger.height(), &set), 1.0);
+  assert_eq!(ledger_coverage(&ledger, 0, &[]), 0.0);
+}
+
+#[test]
+fn uniform_sampling_has_the_requested_size() {
+  let (ledger, owned, _) = ledger();
+  for size in 1..=4 {
+    let set = select(&UniformSampling { size: size + 1 }, &ledger, &[owned]);
+    assert_eq!(set.len(), size + 1);
+    assert_eq!(ledger_coverage(&ledger, ledger.height(), &set), set.len() as f64 / live(&ledger).len() as f64);
+  }
+  assert_eq!(select(&UniformSampling { size: 1000 }, &ledger, &[owned]), live(&ledger));
+  assert_eq!(select(&UniformSampling { size: 0 }, &ledger, &[owned]), vec![owned]);
+}
+
+#[test]
+fn age_matched_decoys_come_from_the_closest_blocks() {
+  let (ledger, owned, _) = ledger();
+  let created = |set: &[usize]| -> Vec<u64> {
+    set.iter().filter(|&&id| id != owned).map(|&id| ledger.account(id).created).collect()
+  };
+
+  assert_eq!(created(&select(&AgeMatched { decoys_per_output: 2 }, &ledger, &[owned])), vec![3, 3]);
+  let mut heights = created(&select(&AgeMatched { decoys_per_output: 6 }, &ledger, &[owned]));
+  heights.sort();
+  assert_eq!(heights, vec![2, 2, 3, 3, 4, 4]);
+}
+
+#[test]
+fn balance_matched_decoys_come_from_the_closest_buckets() {
+  let (ledger, owned, _) = ledger();
+  let balances = |set: &[usize]| -> Vec<u64> {
+    set.iter().filter(|&&id| id != owned).map(|&id| ledger.balance(id)).collect()
+  };
+
+  assert_eq!(balances(&select(&BalanceBucketMatched { decoys_per_output: 2, bucket_width: 10 }, &ledger, &[owned])), vec![30, 35]);
+  let picked = balances(&select(&BalanceBucketMatched { decoys_per_output: 2, bucket_width: 20 }, &ledger, &[owned]));
+  assert!(picked.iter().all(|balance| (20..40).contains(balance)), "picked {:?}", picked);
+}
+
+#[test]
+fn selected_sets_are_proved() {
+  let (ledger, owned, _) = ledger();
+  let height = ledger.height();
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT);
+  let selectors: [&dyn AnonymitySetSelector; 4] = [
+    &WholeLedger,
+    &UniformSampling { size: 4 },
+    &AgeMatched { decoys_per_output: 3 },
+    &BalanceBucketMatched { decoys_per_output: 3, bucket_width: 10 },
+  ];
+
+  for selector in &selectors {
+    let mut exchange = NummatusExchange::from_ledger_with_selector(&ledger, EXCHANGE, height, &table, *selector).unwrap();
+    assert_eq!(exchange.owned_indices().len(), 1);
+    assert!(exchange.generate_proof().verify_against_ledger(&ledger.snapshot(height), None).is_ok(), "{} proof rejected", selector.name());
+    assert_eq!(exchange.reserve_opening(&table).unwrap().0, 33);
+  }
+  assert!(ledger.is_live(owned, height));
+}
@@ -0,0 +1,98 @@
+use rand::thread_rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::SecretKey;
+
+use quisquis::audit::{AuditError, ReserveClaim, audit};
+use quisquis::dlog::DiscreteLogTable;
+use quisquis::ledger::Ledger;
+use quisquis::misc::Nonce;
+use quisquis::nummatus_exchange::NummatusExchange;
+
+const NUM_EXCHANGES: usize = 2;
+
+fn ledger() -> Ledger {
+  let mut rng = thread_rng();
+  let mut ledger = Ledger::simulate(&mut rng, 4, 2, 6);
+  ledger.simulate_transfers(&mut rng, 1, 2);
+  for owner in 0..NUM_EXCHANGES {
+    let key = SecretKey::new(&Secp256k1::with_caps(secp::ContextFlag::Commit), &mut rng);
+    ledger.create_account(&mut rng, owner, key, 50);                //every exchange has non-zero reserves
+  }
+  ledger
+}
+
+// Claim of exchange owner over its own accounts plus the extra ledger ids
+fn claim(ledger: &Ledger, owner: usize, extra: &[usize], nonce: Option<&Nonce>) -> ReserveClaim {
+  let height = ledger.height();
+  let snapshot = ledger.snapshot(height);
+  let table = DiscreteLogTable::new(ledger.total_supply(height));
+  let mut keys: Vec<_> = ledger.owned_accounts(owner, height).iter().map(|&id| ledger.key(id)).collect();
+  keys.extend(extra.iter().map(|&id| ledger.key(id)));
+
+  let mut exchange = NummatusExchange::from_keys(&snapshot, &keys, &table, 2).unwrap();
+  let proof = exchange.generate_proof_with_nonce(nonce);
+  let (total, blinding) = exchange.reserve_opening(&table).unwrap();
+  ReserveClaim { exchange: format!("exchange-{}", owner), proof, total, blinding }
+}
+
+fn reserves(ledger: &Ledger, owner: usize) -> u64 {
+  let height = ledger.height();
+  ledger.owned_accounts(owner, height).iter().map(|&id| ledger.balance(id)).sum()
+}
+
+#[test]
+fn honest_exchanges_pass() {
+  let ledger = ledger();
+  let height = ledger.height();
+  let nonce = Nonce::random(&mut thread_rng());
+  let claims: Vec<_> = (0..NUM_EXCHANGES).map(|owner| claim(&ledger, owner, &[], Some(&nonce))).collect();
+
+  let report = audit(&ledger.snapshot(height), ledger.total_supply(height), Some(&nonce), &claims);
+  assert!(report.passed());
+  assert_eq!(report.height, height);
+  for owner in 0..NUM_EXCHANGES {
+    assert_eq!(report.results[owner].as_ref().ok(), Some(&reserves(&ledger, owner)));
+  }
+  assert_eq!(report.total_reserves(), reserves(&ledger, 0) + reserves(&ledger, 1));
+  assert!(report.double_claims.is_empty());
+
+  // Reserves above the supply fail the audit even when every claim verifies
+  let report = audit(&ledger.snapshot(height), report.total_reserves() - 1, Some(&nonce), &claims);
+  assert!(report.results.iter().all(|result| result.is_ok()));
+  assert!(!report.passed());
+}
+
+#[test]
+fn double_claims_are_flagged() {
+  let ledger = ledger();
+  let height = ledger.height();
+  let snapshot = ledger.snapshot(height);
+  let stolen = ledger.owned_accounts(0, height)[0];
+  let claims = vec![claim(&ledger, 0, &[], None), claim(&ledger, 1, &[stolen], None)];
+
+  let report = audit(&snapshot, ledger.total_supply(height), None, &claims);
+  assert!(report.results.iter().all(|result| result.is_ok()));
+  assert!(!report.passed());
+  assert_eq!(report.double_claims.len(), 1);
+  assert!(snapshot.accounts[report.double_claims[0].account].id == stolen);
+  assert_eq!(report.double_claims[0].exchanges, vec![0, 1]);
+}
+
+#[test]
+fn wrong_openings_and_nonces_are_rejected() {
+  let ledger = ledger();
+  let height = ledger.height();
+  let snapshot = ledger.snapshot(height);
+  let nonce = Nonce::random(&mut thread_rng());
+
+  let mut inflated = claim(&ledger, 0, &[], Some(&nonce));
+  inflated.total += 1;
+  let stale = claim(&ledger, 1, &[], None);
+
+  let report = audit(&snapshot, ledger.total_supply(height), Some(&nonce), &[inflated, stale]);
+  assert!(matches!(report.results[0], Err(AuditError::BadOpening)));
+  assert!(matches!(report.results[1], Err(AuditError::Ledger(_))));
+  assert_eq!(report.total_reserves(), 0);
+  assert!(!report.passed());
+}
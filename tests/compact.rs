@@ -0,0 +1,131 @@
+use rand::thread_rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey, ZERO_KEY};
+
+use quisquis::misc::{Nonce, NONCE_SIZE, POINT_SIZE, SCALAR_SIZE};
+use quisquis::nummatus_compact::{CompactNummatus, COMPACT_NUMMATUS_POK_SIZE};
+use quisquis::nummatus_exchange::NummatusExchange;
+
+const ANON_LIST_SIZE: usize = 4;
+
+fn compact_proof() -> CompactNummatus {
+  NummatusExchange::new(ANON_LIST_SIZE, 2).generate_compact_proof()
+}
+
+#[test]
+fn compact_proofs_verify() {
+  let proof = compact_proof();
+  assert!(proof.verify());
+
+  let mut bytes = Vec::new();
+  proof.write_to(&mut bytes).unwrap();
+  assert_eq!(bytes.len(), POINT_SIZE + SCALAR_SIZE + ANON_LIST_SIZE * (5 * POINT_SIZE + COMPACT_NUMMATUS_POK_SIZE));
+
+  let nonce = Nonce([7u8; NONCE_SIZE]);
+  let proof = NummatusExchange::new(ANON_LIST_SIZE, 2).generate_compact_proof_with_nonce(Some(&nonce));
+  assert!(proof.verify_with_nonce(Some(&nonce)));
+  assert!(!proof.verify());
+}
+
+// Splits with e1 = e leave e2 = 0, which is rejected rather than panicking
+#[test]
+fn tampered_splits_are_rejected() {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let fresh = SecretKey::new(&secp_inst, &mut thread_rng());
+
+  for e1 in &[None, Some(ZERO_KEY), Some(fresh)] {
+    let mut proof = compact_proof();
+    proof.pok_list[1].e1 = e1.clone().unwrap_or_else(|| proof.challenge.clone());
+    assert!(!proof.verify());
+  }
+
+  let mut proof = compact_proof();
+  proof.pok_list[2].s1 = proof.pok_list[2].s2.clone();
+  assert!(!proof.verify());
+}
+
+#[test]
+fn tampered_challenges_are_rejected() {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+  let mut proof = compact_proof();
+  proof.challenge = SecretKey::new(&secp_inst, &mut thread_rng());
+  assert!(!proof.verify());
+
+  let mut proof = compact_proof();
+  proof.challenge = ZERO_KEY;
+  assert!(!proof.verify());
+
+  let mut proof = compact_proof();
+  proof.challenge = proof.pok_list[0].e1.clone();
+  assert!(!proof.verify());
+}
+
+#[test]
+fn tampered_lists_are_rejected() {
+  let mut proof = compact_proof();
+  proof.pok_list.pop();
+  assert!(!proof.verify());
+
+  let mut proof = compact_proof();
+  proof.pubkey_list.pop();
+  assert!(!proof.verify());
+
+  let mut proof = compact_proof();
+  proof.pedersen_com_list.push(proof.pedersen_com_list[0]);
+  assert!(!proof.verify());
+
+  let mut proof = compact_proof();
+  proof.pubkey_list.clear();
+  proof.commitment_list.clear();
+  proof.pedersen_com_list.clear();
+  proof.pok_list.clear();
+  assert!(!proof.verify());
+
+  let mut proof = compact_proof();
+  proof.pedersen_com_list.swap(0, 1);
+  assert!(!proof.verify());
+
+  let mut proof = compact_proof();
+  proof.commitment_list[3].y = PublicKey::new();
+  assert!(!proof.verify());
+
+  // p = d makes the first message of the output the identity
+  let mut proof = compact_proof();
+  proof.pedersen_com_list[0] = proof.commitment_list[0].y;
+  assert!(!proof.verify());
+}
+
+#[test]
+fn compact_proofs_round_trip() {
+  let proof = compact_proof();
+  let mut bytes = Vec::new();
+  proof.write_to(&mut bytes).unwrap();
+
+  let read = CompactNummatus::read_from(&mut &bytes[..]).unwrap();
+  assert!(read.verify());
+  assert_eq!(read.pok_list.len(), ANON_LIST_SIZE);
+  assert!(read.challenge == proof.challenge);
+  assert!(read.h_basepoint() == proof.h_basepoint() && read.g_basepoint() == proof.g_basepoint());
+  let mut rewritten = Vec::new();
+  read.write_to(&mut rewritten).unwrap();
+  assert_eq!(rewritten, bytes);
+
+  // Truncated records, empty proofs, zero scalars and off-curve points are read errors
+  assert!(CompactNummatus::read_from(&mut &bytes[..bytes.len() - 1]).is_err());
+  assert!(CompactNummatus::read_from(&mut &bytes[..POINT_SIZE + SCALAR_SIZE]).is_err());
+
+  let mut zero_challenge = bytes.clone();
+  zero_challenge[POINT_SIZE..POINT_SIZE + SCALAR_SIZE].iter_mut().for_each(|byte| *byte = 0);
+  assert!(CompactNummatus::read_from(&mut &zero_challenge[..]).is_err());
+
+  let mut zero_response = bytes.clone();
+  let last = zero_response.len();
+  zero_response[last - SCALAR_SIZE..].iter_mut().for_each(|byte| *byte = 0);
+  assert!(CompactNummatus::read_from(&mut &zero_response[..]).is_err());
+
+  let mut off_curve = bytes.clone();
+  off_curve[POINT_SIZE + SCALAR_SIZE] = 0x05;
+  assert!(CompactNummatus::read_from(&mut &off_curve[..]).is_err());
+}
@@ -0,0 +1,68 @@
+use std::io;
+
+use quisquis::misc::POINT_SIZE;
+use quisquis::nummatus_exchange::{Nummatus, NummatusExchange};
+use quisquis::nummatus_stream::NUMMATUS_RECORD_SIZE;
+use quisquis::simple_exchange::{Simple, SimpleExchange, SIMPLE_RECORD_SIZE};
+
+const ANON_LIST_SIZE: usize = 3;
+
+fn nummatus_bytes(proof: &Nummatus) -> Vec<u8> {
+  let mut bytes = Vec::new();
+  proof.write_to(&mut bytes).unwrap();
+  bytes
+}
+
+fn simple_bytes(proof: &Simple) -> Vec<u8> {
+  let mut bytes = Vec::new();
+  proof.write_to(&mut bytes).unwrap();
+  bytes
+}
+
+#[test]
+fn decoded_proofs_verify() {
+  let nummatus = NummatusExchange::new(ANON_LIST_SIZE, 1).generate_proof();
+  let bytes = nummatus_bytes(&nummatus);
+  assert_eq!(bytes.len(), POINT_SIZE + ANON_LIST_SIZE * NUMMATUS_RECORD_SIZE);
+  let decoded = Nummatus::read_from(&mut &bytes[..]).unwrap();
+  assert!(decoded.verify());
+  assert_eq!(nummatus_bytes(&decoded), bytes);
+
+  let simple = SimpleExchange::new(ANON_LIST_SIZE).generate_proof();
+  let bytes = simple_bytes(&simple);
+  assert_eq!(bytes.len(), POINT_SIZE + ANON_LIST_SIZE * SIMPLE_RECORD_SIZE);
+  let decoded = Simple::read_from(&mut &bytes[..]).unwrap();
+  assert!(decoded.verify());
+  assert_eq!(simple_bytes(&decoded), bytes);
+}
+
+#[test]
+fn truncated_and_empty_proofs_are_errors() {
+  let bytes = nummatus_bytes(&NummatusExchange::new(ANON_LIST_SIZE, 1).generate_proof());
+  for &len in &[0, POINT_SIZE - 1, POINT_SIZE, POINT_SIZE + 1, bytes.len() - 1] {
+    assert!(Nummatus::read_from(&mut &bytes[..len]).is_err(), "{} bytes decoded", len);
+  }
+
+  let bytes = simple_bytes(&SimpleExchange::new(ANON_LIST_SIZE).generate_proof());
+  for &len in &[0, POINT_SIZE - 1, POINT_SIZE, POINT_SIZE + 1, bytes.len() - 1] {
+    assert!(Simple::read_from(&mut &bytes[..len]).is_err(), "{} bytes decoded", len);
+  }
+
+  let garbage = vec![0xffu8; POINT_SIZE + NUMMATUS_RECORD_SIZE];
+  assert_eq!(Nummatus::read_from(&mut &garbage[..]).err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+}
+
+// Well-formed encodings of statements that make the verifier hit the
+// identity (p = d, or c = h) are rejected rather than panicking
+#[test]
+fn degenerate_statements_are_rejected() {
+  let mut nummatus = Nummatus::read_from(&mut &nummatus_bytes(&NummatusExchange::new(ANON_LIST_SIZE, 1).generate_proof())[..]).unwrap();
+  nummatus.pedersen_com_list[0] = nummatus.commitment_list[0].y;
+  let decoded = Nummatus::read_from(&mut &nummatus_bytes(&nummatus)[..]).unwrap();
+  assert!(!decoded.verify());
+
+  let mut simple = Simple::read_from(&mut &simple_bytes(&SimpleExchange::new(ANON_LIST_SIZE).generate_proof())[..]).unwrap();
+  simple.commitment_list[0].x = simple.h_basepoint();
+  let decoded = Simple::read_from(&mut &simple_bytes(&simple)[..]).unwrap();
+  assert!(!decoded.verify());
+}
@@ -0,0 +1,53 @@
+use rand::thread_rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey};
+
+use quisquis::dlog::{DiscreteLogTable, decrypt_commitment};
+use quisquis::ledger::create_account;
+use quisquis::misc::{GENERATOR_G, GENERATOR_H, MAX_AMOUNT_PER_OUTPUT, amount_to_key, single_base_product};
+
+fn g_to(secp_inst: &Secp256k1, amount: u64) -> PublicKey {
+  single_base_product(secp_inst, PublicKey::from_slice(secp_inst, &GENERATOR_G).unwrap(), amount_to_key(secp_inst, amount))
+}
+
+#[test]
+fn table_solves_every_amount_up_to_its_bound() {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+
+  // 50 is not a square, so the last giant step only partly covers the range
+  for &max_amount in &[1, 2, 49, 50] {
+    let table = DiscreteLogTable::new(max_amount);
+    assert_eq!(table.max_amount(), max_amount);
+    for amount in 1..=max_amount {
+      assert_eq!(table.solve(g_to(&secp_inst, amount)), Some(amount));
+    }
+    for &amount in &[max_amount + 1, max_amount + 2, 2 * max_amount + 7] {
+      assert_eq!(table.solve(g_to(&secp_inst, amount)), None, "{} solved with bound {}", amount, max_amount);
+    }
+  }
+
+  let table = DiscreteLogTable::new(50);
+  assert_eq!(table.solve(PublicKey::from_slice(&secp_inst, &GENERATOR_H).unwrap()), None);
+}
+
+#[test]
+fn commitments_decrypt_to_their_amount() {
+  let mut rng = thread_rng();
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT);
+  let key = SecretKey::new(&secp_inst, &mut rng);
+
+  for &amount in &[0, 1, 2, 31, 32, 33, MAX_AMOUNT_PER_OUTPUT - 1, MAX_AMOUNT_PER_OUTPUT] {
+    let (_, commitment) = create_account(&secp_inst, &mut rng, &key, amount);
+    assert_eq!(decrypt_commitment(&commitment, &key, &table), Some(amount));
+  }
+
+  let (_, commitment) = create_account(&secp_inst, &mut rng, &key, MAX_AMOUNT_PER_OUTPUT + 1);
+  assert_eq!(decrypt_commitment(&commitment, &key, &table), None);
+
+  // Under another key the commitment decrypts to an unrelated point
+  let (_, commitment) = create_account(&secp_inst, &mut rng, &key, 10);
+  let other = SecretKey::new(&secp_inst, &mut rng);
+  assert_eq!(decrypt_commitment(&commitment, &other, &table), None);
+}
@@ -0,0 +1,143 @@
+use rand::thread_rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey, ZERO_KEY};
+
+use quisquis::interactive::{
+  InteractiveProver, InteractiveVerifier, NummatusVerifier, ProtocolError, SimpleProver, SimpleVerifier, run_session,
+};
+use quisquis::misc::QPublicKey;
+use quisquis::nummatus_exchange::NummatusExchange;
+use quisquis::nummatus_nizk::NummatusTranscript;
+use quisquis::simple_exchange::SimpleExchange;
+
+const ANON_LIST_SIZE: usize = 6;
+
+#[test]
+fn honest_sessions_are_accepted() {
+  let nummatus = NummatusExchange::new(ANON_LIST_SIZE, 2);
+  assert!(run_session(nummatus.interactive_prover(), &mut NummatusVerifier::new()).unwrap());
+
+  let simple = SimpleExchange::new(ANON_LIST_SIZE);
+  assert!(run_session(simple.interactive_prover(), &mut SimpleVerifier::new()).unwrap());
+}
+
+// A response to any challenge other than the auditor's does not verify
+#[test]
+fn responses_to_another_challenge_are_rejected() {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let other = SecretKey::new(&secp_inst, &mut thread_rng());
+
+  let mut prover = NummatusExchange::new(ANON_LIST_SIZE, 2).interactive_prover();
+  let mut verifier = NummatusVerifier::new();
+  verifier.challenge(prover.commit().unwrap()).unwrap();
+  assert!(!verifier.verify(prover.respond(&other).unwrap()).unwrap());
+
+  let mut prover = SimpleExchange::new(ANON_LIST_SIZE).interactive_prover();
+  let mut verifier = SimpleVerifier::new();
+  verifier.challenge(prover.commit().unwrap()).unwrap();
+  assert!(!verifier.verify(prover.respond(&other).unwrap()).unwrap());
+}
+
+#[test]
+fn moves_out_of_order_are_refused() {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let challenge = SecretKey::new(&secp_inst, &mut thread_rng());
+
+  let mut prover = NummatusExchange::new(ANON_LIST_SIZE, 2).interactive_prover();
+  assert!(matches!(prover.respond(&challenge), Err(ProtocolError::UnexpectedMessage)));
+  let commitment = prover.commit().unwrap();
+  assert!(matches!(prover.commit(), Err(ProtocolError::UnexpectedMessage)));
+
+  let mut verifier = NummatusVerifier::new();
+  let challenge = verifier.challenge(commitment).unwrap();
+  let response = prover.respond(&challenge).unwrap();
+  assert!(matches!(prover.respond(&challenge), Err(ProtocolError::UnexpectedMessage)));
+  assert!(verifier.verify(response).unwrap());
+
+  // A finished verifier takes no further commitments
+  let fresh = NummatusExchange::new(ANON_LIST_SIZE, 2).interactive_prover().commit().unwrap();
+  assert!(matches!(verifier.challenge(fresh), Err(ProtocolError::UnexpectedMessage)));
+}
+
+#[test]
+fn mismatched_lists_abort_the_session() {
+  let mut prover = NummatusExchange::new(ANON_LIST_SIZE, 2).interactive_prover();
+  let mut verifier = NummatusVerifier::new();
+  let challenge = verifier.challenge(prover.commit().unwrap()).unwrap();
+  let mut response = prover.respond(&challenge).unwrap();
+  response.pok_list.pop();
+  assert!(matches!(verifier.verify(response), Err(ProtocolError::LengthMismatch)));
+
+  // The prover's own check surfaces through run_session
+  let commitment = SimpleExchange::new(ANON_LIST_SIZE).interactive_prover().commit().unwrap();
+  let h_basepoint = commitment.h_basepoint;
+  let lopsided = SimpleProver::new(
+    h_basepoint,
+    commitment.pubkey_list,
+    commitment.commitment_list,
+    commitment.pederson_list,
+    Vec::new(),
+  );
+  assert!(matches!(run_session(lopsided, &mut SimpleVerifier::new()), Err(ProtocolError::LengthMismatch)));
+
+  // An empty statement proves nothing
+  let empty = SimpleProver::new(h_basepoint, Vec::new(), Vec::new(), Vec::new(), Vec::new());
+  assert!(matches!(run_session(empty, &mut SimpleVerifier::new()), Err(ProtocolError::LengthMismatch)));
+}
+
+// Runs an honest Nummatus session up to the response, then lets tamper
+// rewrite the PoK of the first output before the verifier sees it
+fn tampered_nummatus_session(tamper: impl Fn(&mut NummatusTranscript, &SecretKey)) -> Result<bool, ProtocolError> {
+  let mut prover = NummatusExchange::new(ANON_LIST_SIZE, 2).interactive_prover();
+  let commitment = prover.commit().unwrap();
+  let statement = (commitment.pubkey_list[0], commitment.commitment_list[0], commitment.pedersen_com_list[0], commitment.h_basepoint);
+
+  let mut verifier = NummatusVerifier::new();
+  let challenge = verifier.challenge(commitment).unwrap();
+  let mut response = prover.respond(&challenge).unwrap();
+  let mut transcript = NummatusTranscript::from_pok(statement.0, statement.1, statement.2, statement.3, &response.pok_list[0]).unwrap();
+  tamper(&mut transcript, &challenge);
+  response.pok_list[0] = transcript.to_pok();
+  verifier.verify(response)
+}
+
+// A response splitting the challenge as e1 = e, e2 = 0 is rejected, not a panic
+#[test]
+fn malicious_responses_are_rejected() {
+  assert!(tampered_nummatus_session(|_, _| ()).unwrap());
+  assert!(!tampered_nummatus_session(|transcript, challenge| transcript.e1 = challenge.clone()).unwrap());
+}
+
+// Placeholder points abort the session before any curve arithmetic
+#[test]
+fn placeholder_points_are_refused() {
+  let mut commitment = NummatusExchange::new(ANON_LIST_SIZE, 2).interactive_prover().commit().unwrap();
+  commitment.pedersen_com_list[0] = PublicKey::new();
+  assert!(matches!(NummatusVerifier::new().challenge(commitment), Err(ProtocolError::InvalidPoint)));
+
+  let mut commitment = NummatusExchange::new(ANON_LIST_SIZE, 2).interactive_prover().commit().unwrap();
+  commitment.first_messages[1].2 = PublicKey::new();
+  assert!(matches!(NummatusVerifier::new().challenge(commitment), Err(ProtocolError::InvalidPoint)));
+
+  let mut commitment = SimpleExchange::new(ANON_LIST_SIZE).interactive_prover().commit().unwrap();
+  commitment.h_basepoint = PublicKey::new();
+  assert!(matches!(SimpleVerifier::new().challenge(commitment), Err(ProtocolError::InvalidPoint)));
+
+  let mut commitment = SimpleExchange::new(ANON_LIST_SIZE).interactive_prover().commit().unwrap();
+  commitment.pubkey_list[0] = QPublicKey::new();
+  assert!(matches!(SimpleVerifier::new().challenge(commitment), Err(ProtocolError::InvalidPoint)));
+}
+
+#[test]
+fn zero_scalars_are_rejected() {
+  assert!(!tampered_nummatus_session(|transcript, _| transcript.s2 = ZERO_KEY).unwrap());
+  assert!(!tampered_nummatus_session(|transcript, _| transcript.e2 = ZERO_KEY).unwrap());
+
+  let mut prover = SimpleExchange::new(ANON_LIST_SIZE).interactive_prover();
+  let mut verifier = SimpleVerifier::new();
+  let challenge = verifier.challenge(prover.commit().unwrap()).unwrap();
+  let mut response = prover.respond(&challenge).unwrap();
+  response.responses[0] = ZERO_KEY;
+  assert!(!verifier.verify(response).unwrap());
+}
@@ -0,0 +1,91 @@
+use rand::thread_rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::SecretKey;
+
+use quisquis::dlog::DiscreteLogTable;
+use quisquis::keychain::{Keychain, SEED_SIZE};
+use quisquis::ledger::Ledger;
+use quisquis::misc::MAX_AMOUNT_PER_OUTPUT;
+use quisquis::nummatus_exchange::NummatusExchange;
+
+const EXCHANGE: usize = 0;
+const OTHER: usize = 1;
+
+#[test]
+fn keys_are_deterministic_and_distinct() {
+  let keychain = Keychain::from_seed([3u8; SEED_SIZE]);
+  let restored = Keychain::from_seed(keychain.seed());
+  assert!(keychain.derive_keys(0, 8) == restored.derive_keys(0, 8));
+  assert!(keychain.derive_keys(5, 3) == restored.derive_keys(0, 8)[5..]);
+  assert_eq!(keychain.derive_keys(u32::MAX - 2, 8).len(), 2);        //stops at the end of the index range
+
+  let other = Keychain::from_seed([4u8; SEED_SIZE]);
+  let mut keys = keychain.derive_keys(0, 8);
+  keys.extend(other.derive_keys(0, 8));
+  keys.extend(keychain.child(0).derive_keys(0, 8));
+  keys.extend(keychain.child(1).derive_keys(0, 8));
+  for (i, key) in keys.iter().enumerate() {
+    assert!(keys[i + 1..].iter().all(|other| other != key), "key {} repeats", i);
+  }
+}
+
+#[test]
+fn child_keychains_follow_their_path() {
+  let keychain = Keychain::random(&mut thread_rng());
+  assert!(keychain.child(2).seed() == Keychain::from_seed(keychain.seed()).child(2).seed());
+  assert!(keychain.child(2).child(7).seed() == keychain.derive_path(&[2, 7]).seed());
+  assert!(keychain.derive_path(&[]).seed() == keychain.seed());
+  assert!(keychain.derive_path(&[2, 7]).seed() != keychain.derive_path(&[7, 2]).seed());
+  assert!(keychain.child(0).seed() != keychain.seed());
+}
+
+// Keys 0, 1, 3 and 10 own accounts, so the scan stops after 3 unless the
+// gap limit reaches across the 6 unused indices 4..=9
+#[test]
+fn scan_stops_at_the_gap_limit() {
+  let mut rng = thread_rng();
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let keychain = Keychain::random(&mut rng);
+  let mut ledger = Ledger::simulate(&mut rng, 1, 1, 3);
+  for &index in &[0, 1, 3, 10] {
+    ledger.create_account(&mut rng, EXCHANGE, keychain.derive_key(index), 10 + index as u64);
+    let foreign = SecretKey::new(&secp_inst, &mut rng);
+    ledger.create_account(&mut rng, OTHER, foreign, 5);
+  }
+  let snapshot = ledger.snapshot(ledger.height());
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT);
+
+  let amounts = |gap_limit: u32, num_threads: usize| -> Vec<Option<u64>> {
+    keychain.scan(&snapshot, &table, gap_limit, num_threads).iter().map(|found| found.amount).collect()
+  };
+  assert_eq!(amounts(1, 1), vec![Some(10), Some(11)]);
+  assert_eq!(amounts(2, 2), vec![Some(10), Some(11), Some(13)]);
+  assert_eq!(amounts(6, 3), vec![Some(10), Some(11), Some(13)]);
+  assert_eq!(amounts(7, 3), vec![Some(10), Some(11), Some(13), Some(20)]);
+  assert_eq!(amounts(50, 4), vec![Some(10), Some(11), Some(13), Some(20)]);
+
+  // Another branch owns none of them
+  assert!(keychain.child(0).scan(&snapshot, &table, 20, 2).is_empty());
+}
+
+#[test]
+fn exchanges_are_restored_from_a_branch() {
+  let mut rng = thread_rng();
+  let master = Keychain::random(&mut rng);
+  let (hot, cold) = (master.child(0), master.child(1));
+  let mut ledger = Ledger::simulate(&mut rng, 2, 1, 4);
+  ledger.advance();
+  for index in 0..3 {
+    ledger.create_account(&mut rng, EXCHANGE, hot.derive_key(index), 100);
+    ledger.create_account(&mut rng, EXCHANGE, cold.derive_key(index), 200);
+  }
+  let snapshot = ledger.snapshot(ledger.height());
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT);
+
+  let restored = Keychain::from_seed(master.seed()).derive_path(&[1]);
+  let mut exchange = NummatusExchange::from_keychain(&snapshot, &restored, &table, 5, 2).unwrap();
+  assert_eq!(exchange.owned_indices().len(), 3);
+  assert_eq!(exchange.reserve_opening(&table).unwrap().0, 600);
+  assert!(exchange.generate_proof().verify_against_ledger(&snapshot, None).is_ok());
+}
@@ -0,0 +1,34 @@
+use quisquis::leakage::{Samples, analyse, bit_threshold, max_bit_z, two_proportion_z, welch_t, LEAK_THRESHOLD};
+
+#[test]
+fn statistics_detect_differences() {
+  let a: Vec<f64> = (0..100).map(|x| (x % 10) as f64).collect();
+  let b: Vec<f64> = a.iter().map(|x| x + 5.0).collect();
+  assert!(welch_t(&a, &a) == 0.0);
+  assert!(welch_t(&a, &b) < -LEAK_THRESHOLD);
+
+  assert!(two_proportion_z(50, 100, 50, 100) == 0.0);
+  assert!(two_proportion_z(90, 100, 10, 100) > LEAK_THRESHOLD);
+
+  let zeros = [0u8; 4];
+  let ones = [0xffu8; 4];
+  let mixed: Vec<&[u8]> = (0..100).map(|i| if i % 2 == 0 { &zeros[..] } else { &ones[..] }).collect();
+  let all_ones: Vec<&[u8]> = (0..100).map(|_| &ones[..]).collect();
+  assert!(max_bit_z(&mixed, &mixed) == 0.0);
+  assert!(max_bit_z(&mixed, &all_ones) > bit_threshold(32));
+
+  assert!((bit_threshold(1) - LEAK_THRESHOLD).abs() < 1e-9);
+  assert!(bit_threshold(256) > bit_threshold(32) && bit_threshold(264) > bit_threshold(256));
+  assert!(bit_threshold(264) < 6.0);
+}
+
+// Timings are left out: they depend on the machine and its load. With the
+// adjusted thresholds a run fails by chance well under once in 10^4 runs.
+#[test]
+fn published_data_does_not_leak() {
+  let report = analyse(&Samples::collect(10, 40, 20));
+  assert!(report.owned_samples == 200 && report.decoy_samples == 200);
+  for test in report.tests.iter().filter(|test| !test.name.ends_with("time")) {
+    assert!(!test.leaks(), "{} has statistic {}", test.name, test.statistic);
+  }
+}
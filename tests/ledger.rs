@@ -0,0 +1,144 @@
+use rand::thread_rng;
+
+use quisquis::audit::{audit, ReserveClaim};
+use quisquis::dlog::{DiscreteLogTable, decrypt_commitment};
+use quisquis::keychain::Keychain;
+use quisquis::ledger::Ledger;
+use quisquis::misc::MAX_AMOUNT_PER_OUTPUT;
+use quisquis::nummatus_exchange::{AnonymityEntry, LedgerVerifyError, Nummatus, NummatusExchange};
+use quisquis::simple_exchange::SimpleExchange;
+
+const EXCHANGE: usize = 0;
+const OTHER: usize = 1;
+
+// An account whose balance is zero, either from the start or after sending
+// everything away, is still an owned account that can be proved and audited
+#[test]
+fn zero_balance_accounts_are_proved() {
+  let mut rng = thread_rng();
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT);
+  let keychain = Keychain::random(&mut rng);
+  let mut ledger = Ledger::new();
+
+  ledger.advance();
+  let empty = ledger.create_account(&mut rng, EXCHANGE, keychain.derive_key(0), 0);
+  let drained = ledger.create_account(&mut rng, EXCHANGE, keychain.derive_key(1), 40);
+  let funded = ledger.create_account(&mut rng, EXCHANGE, keychain.derive_key(2), 25);
+  let foreign = ledger.create_account(&mut rng, OTHER, keychain.derive_key(100), 10);
+  ledger.advance();
+  let ((drained, _), _) = ledger.transfer(drained, foreign, 40);
+  let height = ledger.height();
+
+  for &id in &[empty, drained] {
+    assert_eq!(ledger.balance(id), 0);
+    assert_eq!(decrypt_commitment(&ledger.account(id).commitment, &ledger.key(id), &table), Some(0));
+  }
+  assert_eq!(ledger.balance(funded), 25);
+
+  let snapshot = ledger.snapshot(height);
+  let mut exchange = NummatusExchange::from_ledger(&ledger, EXCHANGE, height, &table).unwrap();
+  let proof = exchange.generate_proof();
+  assert!(proof.verify_against_ledger(&snapshot, None).is_ok());
+  let (total, blinding) = exchange.reserve_opening(&table).unwrap();
+  assert_eq!(total, 25);
+  let claims = vec![ReserveClaim { exchange: "exchange".to_string(), proof, total, blinding }];
+  assert!(audit(&snapshot, ledger.total_supply(height), None, &claims).passed());
+
+  let mut exchange = NummatusExchange::from_keychain(&snapshot, &keychain, &table, 5, 2).unwrap();
+  assert_eq!(exchange.owned_indices().len(), 3);
+  assert!(exchange.generate_proof().verify_against_ledger(&snapshot, None).is_ok());
+  assert!(SimpleExchange::from_keychain(&snapshot, &keychain, &table, 5, 2).unwrap().generate_proof().verify());
+  assert!(SimpleExchange::from_ledger(&ledger, EXCHANGE, height, &table).unwrap().generate_proof().verify());
+
+  let entries = snapshot.accounts.iter().map(|account| {
+    if ledger.owner(account.id) == EXCHANGE {
+      AnonymityEntry::Owned { pubkey: account.pubkey, commitment: account.commitment, key: ledger.key(account.id), amount: ledger.balance(account.id) }
+    } else {
+      AnonymityEntry::Foreign { pubkey: account.pubkey, commitment: account.commitment }
+    }
+  });
+  let mut bytes = Vec::new();
+  assert_eq!(NummatusExchange::generate_proof_stream(entries, &mut bytes, None).unwrap(), snapshot.accounts.len());
+  assert!(Nummatus::read_from(&mut &bytes[..]).unwrap().verify_against_ledger(&snapshot, None).is_ok());
+}
+
+// Owned amounts above the table bound are reported, so the caller can
+// build a larger table, instead of aborting
+#[test]
+fn amounts_above_the_table_bound_are_errors() {
+  let mut rng = thread_rng();
+  let keychain = Keychain::random(&mut rng);
+  let mut ledger = Ledger::new();
+  ledger.advance();
+  ledger.create_account(&mut rng, OTHER, keychain.derive_key(100), 500);
+  ledger.create_account(&mut rng, EXCHANGE, keychain.derive_key(0), 50);
+  ledger.create_account(&mut rng, EXCHANGE, keychain.derive_key(1), 500);
+  let height = ledger.height();
+  let snapshot = ledger.snapshot(height);
+  let small = DiscreteLogTable::new(100);
+
+  let out_of_range = |result: Result<(), LedgerVerifyError>| match result {
+    Err(LedgerVerifyError::AmountsOutOfRange { indices }) => indices,
+    _ => panic!("expected AmountsOutOfRange"),
+  };
+  assert_eq!(out_of_range(NummatusExchange::from_ledger(&ledger, EXCHANGE, height, &small).map(|_| ())), vec![2]);
+  assert_eq!(out_of_range(NummatusExchange::from_keychain(&snapshot, &keychain, &small, 5, 1).map(|_| ())), vec![2]);
+  assert_eq!(out_of_range(SimpleExchange::from_ledger(&ledger, EXCHANGE, height, &small).map(|_| ())), vec![1]);
+  assert_eq!(out_of_range(SimpleExchange::from_keychain(&snapshot, &keychain, &small, 5, 1).map(|_| ())), vec![1]);
+
+  let large = DiscreteLogTable::new(1000);
+  let exchange = NummatusExchange::from_ledger(&ledger, EXCHANGE, height, &large).unwrap();
+  assert_eq!(out_of_range(exchange.reserve_opening(&small).map(|_| ())), vec![2]);
+  assert_eq!(exchange.reserve_opening(&large).unwrap().0, 550);
+}
+
+#[test]
+fn snapshots_follow_creation_and_spending() {
+  let mut rng = thread_rng();
+  let keychain = Keychain::random(&mut rng);
+  let mut ledger = Ledger::new();
+
+  ledger.advance();
+  let first = ledger.create_account(&mut rng, EXCHANGE, keychain.derive_key(0), 10);
+  let second = ledger.create_account(&mut rng, OTHER, keychain.derive_key(1), 20);
+  ledger.advance();
+  let third = ledger.create_account(&mut rng, EXCHANGE, keychain.derive_key(2), 30);
+  ledger.spend(first);
+  assert_eq!(ledger.height(), 2);
+
+  let ids = |height: u64| -> Vec<usize> { ledger.snapshot(height).accounts.iter().map(|account| account.id).collect() };
+  assert!(ids(0).is_empty());
+  assert_eq!(ids(1), vec![first, second]);
+  assert_eq!(ids(2), vec![second, third]);
+
+  assert!(ledger.is_live(first, 1) && !ledger.is_live(first, 2));
+  assert!(!ledger.is_live(third, 1) && ledger.is_live(third, 2));
+  assert_eq!(ledger.owned_accounts(EXCHANGE, 1), vec![first]);
+  assert_eq!(ledger.owned_accounts(EXCHANGE, 2), vec![third]);
+  assert_eq!(ledger.total_supply(1), 30);
+  assert_eq!(ledger.total_supply(2), 50);
+}
+
+// Transfers keep the supply, and every live commitment decrypts to the
+// balance the ledger records for it under the owner's key
+#[test]
+fn transfers_keep_supply_and_commitments_open_to_balances() {
+  let mut rng = thread_rng();
+  let mut ledger = Ledger::simulate(&mut rng, 3, 2, 4);
+  let supply = ledger.total_supply(ledger.height());
+  let table = DiscreteLogTable::new(supply);                  //transfers can merge balances beyond MAX_AMOUNT_PER_OUTPUT
+  assert_eq!(ledger.num_accounts(), 8);
+
+  ledger.simulate_transfers(&mut rng, 3, 3);
+  let height = ledger.height();
+  assert_eq!(height, 5);
+  assert_eq!(ledger.total_supply(height), supply);
+
+  let snapshot = ledger.snapshot(height);
+  assert_eq!(snapshot.height, height);
+  for account in &snapshot.accounts {
+    assert!(ledger.is_live(account.id, height));
+    assert_eq!(decrypt_commitment(&account.commitment, &ledger.key(account.id), &table), Some(ledger.balance(account.id)));
+  }
+  assert_eq!((0..3).map(|owner| ledger.owned_accounts(owner, height).len()).sum::<usize>(), snapshot.accounts.len());
+}
@@ -0,0 +1,150 @@
+use rand::thread_rng;
+
+use quisquis::dlog::DiscreteLogTable;
+use quisquis::ledger::Ledger;
+use quisquis::merkle::{Hash, MerkleTree, AnchoredNummatus};
+use quisquis::misc::{MAX_AMOUNT_PER_OUTPUT, QPublicKey};
+use quisquis::nummatus_exchange::{NummatusExchange, LedgerVerifyError};
+
+const HEIGHT: u64 = 7;
+
+fn leaves(count: usize) -> Vec<Hash> {
+  (0..count).map(|i| [i as u8 + 1; 32]).collect()
+}
+
+// Every leaf of trees of every shape up to 9 leaves has a proof that
+// verifies, and no other leaf, position, height or root is accepted
+#[test]
+fn membership_proofs_verify() {
+  for count in 1..=9 {
+    let tree = MerkleTree::new(HEIGHT, leaves(count));
+    let root = tree.root();
+    assert_eq!(tree.num_leaves(), count);
+    assert_eq!(tree.height(), HEIGHT);
+
+    for (index, leaf) in tree.leaves().iter().enumerate() {
+      let proof = tree.prove(index);
+      assert!(proof.verify(HEIGHT, &root, leaf), "leaf {} of {} rejected", index, count);
+      assert!(!proof.verify(HEIGHT, &root, &[0u8; 32]));
+      assert!(!proof.verify(HEIGHT + 1, &root, leaf));
+      assert!(!proof.verify(HEIGHT, &[0u8; 32], leaf));
+      if count > 1 {
+        let other = (index + 1) % count;
+        assert!(!proof.verify(HEIGHT, &root, &tree.leaves()[other]));
+      }
+    }
+  }
+}
+
+#[test]
+fn tampered_membership_proofs_are_rejected() {
+  let tree = MerkleTree::new(HEIGHT, leaves(6));
+  let root = tree.root();
+  let leaf = tree.leaves()[4];
+
+  let mut proof = tree.prove(4);
+  proof.index = 5;
+  assert!(!proof.verify(HEIGHT, &root, &leaf));
+
+  let mut proof = tree.prove(4);
+  proof.index = 6;
+  assert!(!proof.verify(HEIGHT, &root, &leaf));
+
+  let mut proof = tree.prove(4);
+  proof.num_leaves = 5;
+  assert!(!proof.verify(HEIGHT, &root, &leaf));
+
+  let mut proof = tree.prove(4);
+  proof.siblings.push([0u8; 32]);
+  assert!(!proof.verify(HEIGHT, &root, &leaf));
+
+  let mut proof = tree.prove(4);
+  proof.siblings.pop();
+  assert!(!proof.verify(HEIGHT, &root, &leaf));
+
+  let mut proof = tree.prove(4);
+  proof.siblings[0][0] ^= 1;
+  assert!(!proof.verify(HEIGHT, &root, &leaf));
+}
+
+// The same accounts at another height give another root
+#[test]
+fn roots_commit_to_the_height() {
+  assert!(MerkleTree::new(HEIGHT, leaves(3)).root() != MerkleTree::new(HEIGHT + 1, leaves(3)).root());
+  assert!(MerkleTree::new(HEIGHT, leaves(3)).root() != MerkleTree::new(HEIGHT, leaves(4)).root());
+  assert!(MerkleTree::new(HEIGHT, leaves(1)).root() != leaves(1)[0]);
+}
+
+#[test]
+fn anchored_proofs_verify_against_the_root() {
+  let mut rng = thread_rng();
+  let ledger = Ledger::simulate(&mut rng, 2, 3, 3);
+  let height = ledger.height();
+  let snapshot = ledger.snapshot(height);
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT);
+  let tree = MerkleTree::from_snapshot(&snapshot);
+  let mut exchange = NummatusExchange::from_ledger(&ledger, 0, height, &table).unwrap();
+
+  let anchored = exchange.generate_anchored_proof(&snapshot, None).unwrap();
+  assert_eq!(anchored.height, height);
+  assert!(anchored.verify(None));
+  assert!(anchored.verify_with_root(height, &tree.root(), None));
+  assert!(!anchored.verify_with_root(height - 1, &tree.root(), None));
+  assert!(!anchored.verify_with_root(height, &[0u8; 32], None));
+
+  // Relabelling the proof with another height breaks every membership proof
+  let mut relabelled = exchange.generate_anchored_proof(&snapshot, None).unwrap();
+  relabelled.height = height - 1;
+  assert!(!relabelled.verify(None));
+
+  let mut duplicated = exchange.generate_anchored_proof(&snapshot, None).unwrap();
+  duplicated.membership_list[1] = duplicated.membership_list[0].clone();
+  assert!(!duplicated.verify(None));
+}
+
+#[test]
+fn entries_outside_the_tree_are_reported() {
+  let mut rng = thread_rng();
+  let mut ledger = Ledger::simulate(&mut rng, 1, 2, 3);
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT);
+  let proof = NummatusExchange::from_ledger(&ledger, 0, ledger.height(), &table).unwrap().generate_proof();
+
+  // The tree at height 1 has only the first block's accounts
+  let tree = MerkleTree::from_snapshot(&ledger.snapshot(1));
+  match AnchoredNummatus::new(proof, &tree) {
+    Err(LedgerVerifyError::UnknownEntries { height, indices }) => {
+      assert_eq!(height, 1);
+      assert_eq!(indices, vec![3, 4, 5]);
+    },
+    _ => panic!("anchored a proof with entries outside the tree"),
+  }
+
+  ledger.advance();
+  ledger.spend(0);
+  let snapshot = ledger.snapshot(ledger.height());
+  let mut exchange = NummatusExchange::from_ledger(&ledger, 0, 2, &table).unwrap();
+  assert!(exchange.generate_anchored_proof(&snapshot, None).is_err());
+}
+
+// Membership lists that do not cover the proof are rejected, not a panic
+#[test]
+fn short_membership_lists_are_rejected() {
+  let mut rng = thread_rng();
+  let ledger = Ledger::simulate(&mut rng, 2, 2, 3);
+  let height = ledger.height();
+  let snapshot = ledger.snapshot(height);
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT);
+  let mut exchange = NummatusExchange::from_ledger(&ledger, 0, height, &table).unwrap();
+
+  let mut shortened = exchange.generate_anchored_proof(&snapshot, None).unwrap();
+  shortened.membership_list.pop();
+  assert!(!shortened.verify(None));
+
+  let mut emptied = exchange.generate_anchored_proof(&snapshot, None).unwrap();
+  emptied.membership_list.clear();
+  assert!(!emptied.verify(None));
+
+  let mut placeholder = exchange.generate_anchored_proof(&snapshot, None).unwrap();
+  placeholder.proof.pubkey_list[0] = QPublicKey::new();
+  assert!(!placeholder.verify(None));
+}
@@ -0,0 +1,223 @@
+use proptest::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey, ONE_KEY};
+
+use quisquis::dlog::DiscreteLogTable;
+use quisquis::ledger::{Ledger, create_account};
+use quisquis::misc::{Nonce, GENERATOR_G, GENERATOR_H, MAX_AMOUNT_PER_OUTPUT, SCALAR_SIZE};
+use quisquis::misc::{a_minus_bx, amount_to_key, checked_ratio, pedersen_commitment, ratio, scalar_is_zero, select_scalar, single_base_product};
+use quisquis::misc::uniform_pedersen_commitment;
+use quisquis::nummatus_exchange::NummatusExchange;
+use quisquis::nummatus_nizk::NummatusPoK;
+use quisquis::simple_exchange::SimpleExchange;
+use quisquis::simple_nizk::SimplePoK;
+
+// Every case sets up a fresh signing context, so keep the case counts low
+const FAST_CASES: u32 = 64;
+const PROOF_CASES: u32 = 12;
+
+fn scalar() -> impl Strategy<Value = SecretKey> {
+  any::<[u8; SCALAR_SIZE]>().prop_filter_map("not a valid scalar", |bytes| SecretKey::from_slice(&Secp256k1::without_caps(), &bytes).ok())
+}
+
+fn nonce() -> impl Strategy<Value = Option<Nonce>> {
+  proptest::option::of(any::<[u8; 32]>().prop_map(Nonce))
+}
+
+fn commit_context() -> Secp256k1 {
+  Secp256k1::with_caps(secp::ContextFlag::Commit)
+}
+
+fn basepoints(secp_inst: &Secp256k1) -> (PublicKey, PublicKey) {
+  (PublicKey::from_slice(secp_inst, &GENERATOR_G).unwrap(), PublicKey::from_slice(secp_inst, &GENERATOR_H).unwrap())
+}
+
+fn point_sum(secp_inst: &Secp256k1, points: &[PublicKey]) -> PublicKey {
+  PublicKey::from_combination(secp_inst, points.iter().collect()).unwrap()
+}
+
+//flips one bit of the serialized PoK; None when the result no longer decodes
+fn flip_bit<F, T>(bytes: &[u8], bit: usize, decode: F) -> Option<T>
+  where F: Fn(&mut &[u8]) -> std::io::Result<T> {
+  let mut mutated = bytes.to_vec();
+  mutated[bit / 8] ^= 1 << (bit % 8);
+  decode(&mut &mutated[..]).ok()
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig::with_cases(FAST_CASES))]
+
+  #[test]
+  fn a_minus_bx_inverts_b_times_x(a in scalar(), b in scalar(), x in scalar()) {
+    let secp_inst = Secp256k1::without_caps();
+    let mut bx = b.clone();
+    bx.mul_assign(&secp_inst, &x).unwrap();
+    prop_assume!(bx != a);
+
+    let mut result = a_minus_bx(&secp_inst, a.clone(), b.clone(), x.clone());
+    result.add_assign(&secp_inst, &bx).unwrap();
+    prop_assert_eq!(result, a.clone());
+
+    // a - 1*x and a - x*1 agree
+    prop_assume!(a != x);
+    prop_assert_eq!(a_minus_bx(&secp_inst, a.clone(), ONE_KEY, x.clone()), a_minus_bx(&secp_inst, a, x, ONE_KEY));
+  }
+
+  #[test]
+  fn amount_to_key_is_additive(v1 in 1..u64::MAX / 2, v2 in 1..u64::MAX / 2) {
+    let secp_inst = Secp256k1::without_caps();
+    let mut sum = amount_to_key(&secp_inst, v1);
+    sum.add_assign(&secp_inst, &amount_to_key(&secp_inst, v2)).unwrap();
+    prop_assert_eq!(sum, amount_to_key(&secp_inst, v1 + v2));
+  }
+
+  #[test]
+  fn select_scalar_picks_by_choice(a in scalar(), b in scalar()) {
+    prop_assert_eq!(select_scalar(1, &a, &b), a.clone());
+    prop_assert_eq!(select_scalar(0, &a, &b), b);
+    prop_assert_eq!(scalar_is_zero(&a), 0);
+  }
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig::with_cases(PROOF_CASES))]
+
+  // num * den^-1 * den == num, and num * num^-1 is the identity, which
+  // cannot be represented as a PublicKey: ratio panics, checked_ratio gives None
+  #[test]
+  fn ratio_undoes_multiplication(k1 in scalar(), k2 in scalar()) {
+    prop_assume!(k1 != k2);
+    let secp_inst = commit_context();
+    let (g_basepoint, _) = basepoints(&secp_inst);
+    let num = single_base_product(&secp_inst, g_basepoint, k1);
+    let den = single_base_product(&secp_inst, g_basepoint, k2);
+
+    prop_assert_eq!(point_sum(&secp_inst, &[ratio(&secp_inst, num, den), den]), num);
+    prop_assert!(std::panic::catch_unwind(|| ratio(&secp_inst, num, num)).is_err());
+    prop_assert_eq!(checked_ratio(&secp_inst, num, den), Some(ratio(&secp_inst, num, den)));
+    prop_assert!(checked_ratio(&secp_inst, num, num).is_none());
+  }
+
+  // The same commitment as pedersen_commitment, including h^k for amount 0
+  #[test]
+  fn uniform_pedersen_commitment_matches(key in scalar(), amount in 0..MAX_AMOUNT_PER_OUTPUT) {
+    let secp_inst = commit_context();
+    let (g_basepoint, h_basepoint) = basepoints(&secp_inst);
+    let uniform = uniform_pedersen_commitment(&secp_inst, &mut rand::thread_rng(), g_basepoint, h_basepoint, amount, key.clone());
+    prop_assert_eq!(uniform, pedersen_commitment(&secp_inst, g_basepoint, h_basepoint, amount, key.clone()));
+    prop_assert_eq!(uniform_pedersen_commitment(&secp_inst, &mut rand::thread_rng(), g_basepoint, h_basepoint, 0, key.clone()),
+                    single_base_product(&secp_inst, h_basepoint, key));
+  }
+
+  #[test]
+  fn honest_pok_verifies_and_bit_flips_fail(key in scalar(), amount in 1..MAX_AMOUNT_PER_OUTPUT, beta in scalar(),
+                                            nonce in nonce(), bit in 0..4 * SCALAR_SIZE * 8) {
+    let secp_inst = commit_context();
+    let (g_basepoint, h_basepoint) = basepoints(&secp_inst);
+    let mut rng = rand::thread_rng();
+    let (pubkey, commitment) = create_account(&secp_inst, &mut rng, &key, amount);
+    let nonce = nonce.as_ref();
+
+    // SimplePoK
+    let pederson = pedersen_commitment(&secp_inst, g_basepoint, h_basepoint, amount, key.clone());
+    let pok = SimplePoK::create_pok_from_representation(pubkey, commitment, pederson, key.clone(), h_basepoint, nonce);
+    prop_assert!(SimplePoK::verify_pok(pubkey, commitment, pederson, h_basepoint, pok.clone(), nonce));
+    let mut bytes = Vec::new();
+    pok.write_to(&mut bytes).unwrap();
+    if let Some(mutated) = flip_bit(&bytes, bit % (2 * SCALAR_SIZE * 8), |reader| SimplePoK::read_from(&secp_inst, reader)) {
+      prop_assert!(!SimplePoK::verify_pok(pubkey, commitment, pederson, h_basepoint, mutated, nonce));
+    }
+
+    // NummatusPoK of an owned output and of a decoy
+    let decoy_com = single_base_product(&secp_inst, h_basepoint, beta.clone());
+    let owned_pok = NummatusPoK::create_pok_from_representation(pubkey, commitment, pederson, key, h_basepoint, nonce);
+    let decoy_pok = NummatusPoK::create_pok_from_decoy(pubkey, commitment, decoy_com, beta, h_basepoint, nonce);
+    for (pedersen_com, pok) in [(pederson, owned_pok), (decoy_com, decoy_pok)] {
+      prop_assert!(NummatusPoK::verify_pok(pubkey, commitment, pedersen_com, h_basepoint, pok.clone(), nonce));
+      let mut bytes = Vec::new();
+      pok.write_to(&mut bytes).unwrap();
+      if let Some(mutated) = flip_bit(&bytes, bit, |reader| NummatusPoK::read_from(&secp_inst, reader)) {
+        prop_assert!(!NummatusPoK::verify_pok(pubkey, commitment, pedersen_com, h_basepoint, mutated, nonce));
+      }
+    }
+  }
+
+  #[test]
+  fn honest_exchange_proofs_verify(anon_list_size in 1..8usize, own_percent in 0..=100usize, nonce in nonce()) {
+    let own_list_size = (anon_list_size * own_percent / 100).max(1);
+    let nonce = nonce.as_ref();
+
+    let nummatus = NummatusExchange::new(anon_list_size, own_list_size).generate_proof_with_nonce(nonce);
+    prop_assert!(nummatus.verify_with_nonce(nonce));
+
+    let simple = SimpleExchange::new(own_list_size).generate_proof_with_nonce(nonce);
+    prop_assert!(simple.verify_with_nonce(nonce));
+  }
+
+  // Replacing any one point of the statement, or any one PoK, of a valid
+  // proof with that of another entry or a fresh point makes it fail
+  #[test]
+  fn mutated_exchange_proofs_fail(anon_list_size in 2..6usize, index in any::<prop::sample::Index>(),
+                                  field in 0..6usize, replacement in scalar()) {
+    let secp_inst = commit_context();
+    let (g_basepoint, _) = basepoints(&secp_inst);
+    let point = single_base_product(&secp_inst, g_basepoint, replacement);
+    let i = index.index(anon_list_size);
+    let j = (i + 1) % anon_list_size;
+
+    let mut nummatus = NummatusExchange::new(anon_list_size, anon_list_size / 2).generate_proof();
+    match field {
+      0 => nummatus.pubkey_list[i].x = point,
+      1 => nummatus.pubkey_list[i].y = point,
+      2 => nummatus.commitment_list[i].x = point,
+      3 => nummatus.commitment_list[i].y = point,
+      4 => nummatus.pedersen_com_list[i] = point,
+      _ => nummatus.pok_list[i] = nummatus.pok_list[j].clone(),
+    }
+    prop_assert!(!nummatus.verify());
+
+    let mut simple = SimpleExchange::new(anon_list_size).generate_proof();
+    match field {
+      0 => simple.pubkey_list[i].x = point,
+      1 => simple.pubkey_list[i].y = point,
+      2 => simple.commitment_list[i].x = point,
+      3 => simple.commitment_list[i].y = point,
+      4 => simple.pederson_list[i] = point,
+      _ => simple.pok_list[i] = simple.pok_list[j].clone(),
+    }
+    prop_assert!(!simple.verify());
+  }
+
+  // The product of the published Pedersen commitments opens to the sum of
+  // the balances the exchange owns on the ledger
+  #[test]
+  fn reserve_commitment_opens_to_true_total(seed in any::<u64>(), num_blocks in 1..3u64, accounts_per_block in 1..5usize) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let ledger = Ledger::simulate(&mut rng, 2, num_blocks, accounts_per_block);
+    let height = ledger.height();
+    let owned = ledger.owned_accounts(0, height);
+    prop_assume!(!owned.is_empty());
+
+    let secp_inst = commit_context();
+    let (g_basepoint, h_basepoint) = basepoints(&secp_inst);
+    let table = DiscreteLogTable::new(ledger.total_supply(height));
+    let total: u64 = owned.iter().map(|&id| ledger.balance(id)).sum();
+
+    let mut n_exch = NummatusExchange::from_ledger(&ledger, 0, height, &table).unwrap();
+    let (opened_total, blinding) = n_exch.reserve_opening(&table).unwrap();
+    prop_assert_eq!(opened_total, total);
+    let proof = n_exch.generate_proof();
+    prop_assert_eq!(proof.reserve_commitment(), Some(pedersen_commitment(&secp_inst, g_basepoint, h_basepoint, total, blinding)));
+
+    let simple = SimpleExchange::from_ledger(&ledger, 0, height, &table).unwrap().generate_proof();
+    let mut keys = owned.iter().map(|&id| ledger.key(id));
+    let mut key_sum = keys.next().unwrap();
+    for key in keys {
+      key_sum.add_assign(&secp_inst, &key).unwrap();
+    }
+    prop_assert_eq!(point_sum(&secp_inst, &simple.pederson_list), pedersen_commitment(&secp_inst, g_basepoint, h_basepoint, total, key_sum));
+  }
+}
@@ -0,0 +1,79 @@
+use rand::thread_rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::SecretKey;
+
+use quisquis::dlog::DiscreteLogTable;
+use quisquis::ledger::{Ledger, LedgerSnapshot};
+use quisquis::misc::MAX_AMOUNT_PER_OUTPUT;
+use quisquis::scan::{ScanMatch, scan, scan_parallel};
+
+const EXCHANGE: usize = 0;
+
+fn summary(matches: &[ScanMatch]) -> Vec<(usize, usize, Option<u64>)> {
+  matches.iter().map(|found| (found.index, found.id, found.amount)).collect()
+}
+
+// The accounts of EXCHANGE in a ledger with some spent accounts, as
+// (position in the snapshot, ledger id, balance) in snapshot order
+fn ledger() -> (Ledger, Vec<(usize, usize, Option<u64>)>) {
+  let mut rng = thread_rng();
+  let mut ledger = Ledger::simulate(&mut rng, 3, 3, 5);
+  let key = SecretKey::new(&Secp256k1::with_caps(secp::ContextFlag::Commit), &mut rng);
+  ledger.create_account(&mut rng, EXCHANGE, key, MAX_AMOUNT_PER_OUTPUT);   //at least one account, above the small bound below
+  ledger.simulate_transfers(&mut rng, 2, 2);
+  let height = ledger.height();
+
+  let expected = ledger.snapshot(height).accounts.iter().enumerate()
+    .filter(|(_, account)| ledger.owner(account.id) == EXCHANGE)
+    .map(|(index, account)| (index, account.id, Some(ledger.balance(account.id))))
+    .collect();
+  (ledger, expected)
+}
+
+#[test]
+fn scan_finds_the_owned_accounts() {
+  let (ledger, expected) = ledger();
+  let height = ledger.height();
+  let snapshot = ledger.snapshot(height);
+  let keys: Vec<_> = ledger.owned_accounts(EXCHANGE, height).iter().map(|&id| ledger.key(id)).collect();
+  let table = DiscreteLogTable::new(ledger.total_supply(height));
+
+  let matches = scan(&snapshot, &keys, &table);
+  assert_eq!(summary(&matches), expected);
+  for found in &matches {
+    assert!(found.key == ledger.key(found.id));
+  }
+
+  assert!(scan(&snapshot, &[], &table).is_empty());
+  assert!(scan(&LedgerSnapshot { height, accounts: Vec::new() }, &keys, &table).is_empty());
+}
+
+// Amounts above the table bound are reported as None, not as missing accounts
+#[test]
+fn amounts_above_the_bound_are_unknown() {
+  let (ledger, expected) = ledger();
+  let height = ledger.height();
+  let keys: Vec<_> = ledger.owned_accounts(EXCHANGE, height).iter().map(|&id| ledger.key(id)).collect();
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT / 2);
+
+  let bounded: Vec<_> = expected.iter()
+    .map(|&(index, id, amount)| (index, id, amount.filter(|&amount| amount <= MAX_AMOUNT_PER_OUTPUT / 2)))
+    .collect();
+  assert_eq!(summary(&scan(&ledger.snapshot(height), &keys, &table)), bounded);
+}
+
+#[test]
+fn parallel_scan_agrees_with_scan() {
+  let (ledger, expected) = ledger();
+  let height = ledger.height();
+  let snapshot = ledger.snapshot(height);
+  let keys: Vec<_> = ledger.owned_accounts(EXCHANGE, height).iter().map(|&id| ledger.key(id)).collect();
+  let table = DiscreteLogTable::new(ledger.total_supply(height));
+
+  // Including more threads than accounts, so that some threads get no chunk
+  for &num_threads in &[1, 2, 3, 4, 7, snapshot.accounts.len(), snapshot.accounts.len() + 5] {
+    assert_eq!(summary(&scan_parallel(&snapshot, &keys, &table, num_threads)), expected, "{} threads", num_threads);
+  }
+  assert!(scan_parallel(&LedgerSnapshot { height, accounts: Vec::new() }, &keys, &table, 4).is_empty());
+}
@@ -0,0 +1,149 @@
+use rand::thread_rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey};
+
+use quisquis::ledger::create_account;
+use quisquis::misc::{QPublicKey, GENERATOR_G, GENERATOR_H, MAX_AMOUNT_PER_OUTPUT, MINUS_ONE_KEY};
+use quisquis::misc::{pedersen_commitment, single_base_product};
+use quisquis::nummatus_nizk::{NummatusPoK, NummatusTranscript};
+
+const NUM_SAMPLES: usize = 64;
+
+// One anonymity-set entry with the PoK an exchange would publish for it
+struct Entry {
+  pubkey: QPublicKey,
+  commitment: QPublicKey,
+  pedersen_com: PublicKey,
+  h_basepoint: PublicKey,
+  pok: NummatusPoK,
+}
+
+impl Entry {
+  fn transcript_opt(&self) -> Option<NummatusTranscript> {
+    NummatusTranscript::from_pok(self.pubkey, self.commitment, self.pedersen_com, self.h_basepoint, &self.pok)
+  }
+
+  fn transcript(&self) -> NummatusTranscript {
+    self.transcript_opt().unwrap()
+  }
+
+  fn simulate(&self, challenge: &SecretKey) -> NummatusTranscript {
+    NummatusTranscript::simulate(&mut thread_rng(), self.pubkey, self.commitment, self.pedersen_com, self.h_basepoint, challenge).unwrap()
+  }
+
+  fn accepts(&self, transcript: &NummatusTranscript) -> bool {
+    transcript.is_accepting(self.pubkey, self.commitment, self.pedersen_com, self.h_basepoint)
+  }
+}
+
+//an account of the exchange, proved with its secret key
+fn owned_entry(secp_inst: &Secp256k1) -> Entry {
+  let mut rng = thread_rng();
+  let g_basepoint = PublicKey::from_slice(secp_inst, &GENERATOR_G).unwrap();
+  let h_basepoint = PublicKey::from_slice(secp_inst, &GENERATOR_H).unwrap();
+  let key = SecretKey::new(secp_inst, &mut rng);
+  let balance = 1 + MAX_AMOUNT_PER_OUTPUT / 2;
+  let (pubkey, commitment) = create_account(secp_inst, &mut rng, &key, balance);
+  let pedersen_com = pedersen_commitment(secp_inst, g_basepoint, h_basepoint, balance, key.clone());
+  let pok = NummatusPoK::create_pok_from_representation(pubkey, commitment, pedersen_com, key, h_basepoint, None);
+  Entry { pubkey, commitment, pedersen_com, h_basepoint, pok }
+}
+
+//an account of someone else, proved with a commitment to zero
+fn decoy_entry(secp_inst: &Secp256k1) -> Entry {
+  let mut rng = thread_rng();
+  let h_basepoint = PublicKey::from_slice(secp_inst, &GENERATOR_H).unwrap();
+  let key = SecretKey::new(secp_inst, &mut rng);
+  let (pubkey, commitment) = create_account(secp_inst, &mut rng, &key, MAX_AMOUNT_PER_OUTPUT / 2);
+  let beta = SecretKey::new(secp_inst, &mut rng);
+  let pedersen_com = single_base_product(secp_inst, h_basepoint, beta.clone());
+  let pok = NummatusPoK::create_pok_from_decoy(pubkey, commitment, pedersen_com, beta, h_basepoint, None);
+  Entry { pubkey, commitment, pedersen_com, h_basepoint, pok }
+}
+
+//fraction of set bits in each of e1, e2, s1, s2 over a set of transcripts
+fn bit_balance(transcripts: &[NummatusTranscript]) -> [f64; 4] {
+  let mut ones = [0u32; 4];
+  for transcript in transcripts {
+    for (count, scalar) in ones.iter_mut().zip(&[&transcript.e1, &transcript.e2, &transcript.s1, &transcript.s2]) {
+      *count += scalar.0.iter().map(|byte| byte.count_ones()).sum::<u32>();
+    }
+  }
+  let total = (transcripts.len() * 256) as f64;
+  [ones[0] as f64 / total, ones[1] as f64 / total, ones[2] as f64 / total, ones[3] as f64 / total]
+}
+
+#[test]
+fn real_transcripts_are_accepting() {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  for entry in &[owned_entry(&secp_inst), decoy_entry(&secp_inst)] {
+    assert!(NummatusPoK::verify_pok(entry.pubkey, entry.commitment, entry.pedersen_com, entry.h_basepoint, entry.pok.clone(), None));
+    assert!(entry.accepts(&entry.transcript()));
+  }
+}
+
+#[test]
+fn simulated_transcripts_accept_without_a_witness() {
+  let mut rng = thread_rng();
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  for entry in &[owned_entry(&secp_inst), decoy_entry(&secp_inst)] {
+    let challenge = SecretKey::new(&secp_inst, &mut rng);
+    let simulated = entry.simulate(&challenge);
+    assert!(entry.accepts(&simulated));
+    assert!(simulated.challenge() == Some(challenge));
+  }
+}
+
+// Statements and splits no transcript can answer give None rather than a panic
+#[test]
+fn degenerate_transcripts_are_none() {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let challenge = SecretKey::new(&secp_inst, &mut thread_rng());
+
+  // p = d makes the first message the identity
+  let entry = owned_entry(&secp_inst);
+  let degenerate = Entry { pedersen_com: entry.commitment.y, ..entry };
+  assert!(degenerate.transcript_opt().is_none());
+  assert!(NummatusTranscript::simulate(&mut thread_rng(), degenerate.pubkey, degenerate.commitment, degenerate.pedersen_com, degenerate.h_basepoint, &challenge).is_none());
+
+  let mut transcript = decoy_entry(&secp_inst).transcript();
+  transcript.e2 = transcript.e1.clone();
+  transcript.e2.mul_assign(&secp_inst, &MINUS_ONE_KEY).unwrap();
+  assert!(transcript.challenge().is_none());
+}
+
+// For the challenge of a real transcript, the simulator gives another
+// accepting transcript of the same statement. It is only a simulator for an
+// honest verifier: the Fiat-Shamir verifier still rejects it, since its
+// challenge is not the hash of its first messages.
+#[test]
+fn simulated_transcripts_match_real_challenges() {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  for entry in &[owned_entry(&secp_inst), decoy_entry(&secp_inst)] {
+    let real = entry.transcript();
+    let simulated = entry.simulate(&real.challenge().unwrap());
+    assert!(entry.accepts(&simulated));
+    assert!(simulated.challenge() == real.challenge());
+    assert!(simulated != real);
+    assert!(!NummatusPoK::verify_pok(entry.pubkey, entry.commitment, entry.pedersen_com, entry.h_basepoint, simulated.to_pok(), None));
+  }
+}
+
+// Real owned, real decoy and simulated transcripts all have uniformly
+// distributed scalars: each bit is set about half the time
+#[test]
+fn transcript_scalars_are_balanced() {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let owned: Vec<NummatusTranscript> = (0..NUM_SAMPLES).map(|_| owned_entry(&secp_inst).transcript()).collect();
+  let decoy: Vec<NummatusTranscript> = (0..NUM_SAMPLES).map(|_| decoy_entry(&secp_inst).transcript()).collect();
+  let entry = owned_entry(&secp_inst);
+  let simulated: Vec<NummatusTranscript> = owned.iter().map(|real| entry.simulate(&real.challenge().unwrap())).collect();
+
+  // 16384 bits per scalar and kind, so 0.03 is more than 7 standard deviations
+  for (kind, transcripts) in &[("owned", &owned), ("decoy", &decoy), ("simulated", &simulated)] {
+    for (name, balance) in ["e1", "e2", "s1", "s2"].iter().zip(&bit_balance(transcripts)) {
+      assert!((balance - 0.5).abs() < 0.03, "{} of {} transcripts has bit balance {}", name, kind, balance);
+    }
+  }
+}
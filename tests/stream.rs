@@ -0,0 +1,83 @@
+use std::io;
+
+use rand::thread_rng;
+
+use quisquis::dlog::DiscreteLogTable;
+use quisquis::ledger::Ledger;
+use quisquis::misc::{MAX_AMOUNT_PER_OUTPUT, POINT_SIZE};
+use quisquis::nummatus_exchange::{LedgerVerifyError, Nummatus, NummatusExchange};
+use quisquis::nummatus_stream::{NummatusStreamVerifier, NUMMATUS_RECORD_SIZE};
+
+const ANON_LIST_SIZE: usize = 4;
+
+fn written_proof() -> Vec<u8> {
+  let mut bytes = Vec::new();
+  NummatusExchange::new(ANON_LIST_SIZE, 2).write_proof(&mut bytes, None).unwrap();
+  bytes
+}
+
+#[test]
+fn written_proof_verifies_as_a_stream() {
+  let bytes = written_proof();
+  assert_eq!(bytes.len(), POINT_SIZE + ANON_LIST_SIZE * NUMMATUS_RECORD_SIZE);
+
+  let mut reader = &bytes[..];
+  let mut verifier = NummatusStreamVerifier::from_header(&mut reader).unwrap();
+  assert!(verifier.verify_all(&mut reader).unwrap());
+  assert_eq!(verifier.num_records(), ANON_LIST_SIZE);
+
+  let proof = Nummatus::read_from(&mut &bytes[..]).unwrap();
+  assert!(verifier.reserve_commitment().is_some());
+  assert_eq!(verifier.reserve_commitment(), proof.reserve_commitment());
+}
+
+#[test]
+fn truncated_stream_is_an_error() {
+  let bytes = written_proof();
+  let mut reader = &bytes[..bytes.len() - 1];
+  let mut verifier = NummatusStreamVerifier::from_header(&mut reader).unwrap();
+  let err = verifier.verify_all(&mut reader).unwrap_err();
+  assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+  assert_eq!(verifier.num_records(), ANON_LIST_SIZE - 1);
+
+  // A header alone is a proof without records
+  let mut reader = &bytes[..POINT_SIZE];
+  let mut verifier = NummatusStreamVerifier::from_header(&mut reader).unwrap();
+  assert!(!verifier.verify_all(&mut reader).unwrap());
+}
+
+// Flipping the lowest bit of s2 in the second record keeps it decodable, but
+// verification stops there and only the first record is absorbed
+#[test]
+fn tampered_record_is_rejected() {
+  let mut bytes = written_proof();
+  bytes[POINT_SIZE + 2 * NUMMATUS_RECORD_SIZE - 1] ^= 1;
+
+  let mut reader = &bytes[..];
+  let mut verifier = NummatusStreamVerifier::from_header(&mut reader).unwrap();
+  assert!(!verifier.verify_all(&mut reader).unwrap());
+  assert_eq!(verifier.num_records(), 1);
+}
+
+// The stream verifier keeps no per-record state, so a repeated record still
+// verifies; the ledger check is what rejects it
+#[test]
+fn repeated_record_is_left_to_the_ledger_check() {
+  let mut rng = thread_rng();
+  let ledger = Ledger::simulate(&mut rng, 2, 2, 3);
+  let height = ledger.height();
+  let snapshot = ledger.snapshot(height);
+  let table = DiscreteLogTable::new(MAX_AMOUNT_PER_OUTPUT);
+  let mut bytes = Vec::new();
+  NummatusExchange::from_ledger(&ledger, 0, height, &table).unwrap().write_proof(&mut bytes, None).unwrap();
+  let record = bytes[POINT_SIZE..POINT_SIZE + NUMMATUS_RECORD_SIZE].to_vec();
+  bytes.extend_from_slice(&record);
+
+  let mut reader = &bytes[..];
+  let mut verifier = NummatusStreamVerifier::from_header(&mut reader).unwrap();
+  assert!(verifier.verify_all(&mut reader).unwrap());
+
+  let proof = Nummatus::read_from(&mut &bytes[..]).unwrap();
+  assert!(proof.verify());
+  assert!(matches!(proof.verify_against_ledger(&snapshot, None), Err(LedgerVerifyError::DuplicateEntries { .. })));
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use quisquis::sweep::{SizeList, OutputFormat, SweepRow, write_rows, with_default_subcommand, parse_num_iter};
+
+fn row(protocol: &'static str, anon_list_size: usize, own_list_size: usize) -> SweepRow {
+  SweepRow {
+    protocol,
+    anon_list_size,
+    own_list_size,
+    num_iter: 3,
+    setup_time: Duration::from_millis(1500),
+    gen_time: Duration::from_micros(250),
+    ver_time: Duration::from_nanos(7),
+    proof_size: 29333,
+  }
+}
+
+fn written(format: OutputFormat, rows: &[SweepRow]) -> String {
+  let mut bytes = Vec::new();
+  write_rows(&mut bytes, format, rows).unwrap();
+  String::from_utf8(bytes).unwrap()
+}
+
+fn args(line: &str) -> Vec<String> {
+  line.split_whitespace().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn size_lists_parse() {
+  assert_eq!("100,1000, 10000".parse::<SizeList>().unwrap().0, vec![100, 1000, 10000]);
+  assert_eq!("7".parse::<SizeList>().unwrap().0, vec![7]);
+  assert_eq!("250:1000:250".parse::<SizeList>().unwrap().0, vec![250, 500, 750, 1000]);
+  assert_eq!("100:350:100".parse::<SizeList>().unwrap().0, vec![100, 200, 300]);
+  assert_eq!("5:5:1".parse::<SizeList>().unwrap().0, vec![5]);
+
+  for bad in &["", "1,,2", "1,x", "-1", "1:10", "1:10:2:3", "1:10:0", "10:1:1", "a:10:1"] {
+    assert!(bad.parse::<SizeList>().is_err(), "{:?} parsed", bad);
+  }
+}
+
+#[test]
+fn iteration_counts_parse() {
+  assert_eq!(parse_num_iter("1"), Ok(1));
+  assert_eq!(parse_num_iter("100"), Ok(100));
+  for bad in &["0", "", "-1", "x"] {
+    assert!(parse_num_iter(bad).is_err(), "{:?} parsed", bad);
+  }
+}
+
+#[test]
+fn output_formats_parse() {
+  assert!(matches!("csv".parse::<OutputFormat>(), Ok(OutputFormat::Csv)));
+  assert!(matches!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json)));
+  assert!("CSV".parse::<OutputFormat>().is_err());
+  assert!("xml".parse::<OutputFormat>().is_err());
+}
+
+#[test]
+fn rows_are_written_as_csv() {
+  let rows = [row("nummatus", 100, 25), row("nummatus-compact", 100, 25)];
+  assert_eq!(written(OutputFormat::Csv, &rows),
+    "protocol,anon_list_size,own_list_size,num_iter,setup_time_s,gen_time_s,ver_time_s,proof_size_bytes\n\
+     nummatus,100,25,3,1.500000000,0.000250000,0.000000007,29333\n\
+     nummatus-compact,100,25,3,1.500000000,0.000250000,0.000000007,29333\n");
+  assert_eq!(written(OutputFormat::Csv, &[]).lines().count(), 1);
+}
+
+#[test]
+fn rows_are_written_as_json() {
+  let rows = [row("simple", 25, 25), row("simple", 50, 50)];
+  assert_eq!(written(OutputFormat::Json, &rows),
+    "[\n  \
+     {\"protocol\": \"simple\", \"anon_list_size\": 25, \"own_list_size\": 25, \"num_iter\": 3, \
+     \"setup_time_s\": 1.500000000, \"gen_time_s\": 0.000250000, \"ver_time_s\": 0.000000007, \"proof_size_bytes\": 29333},\n  \
+     {\"protocol\": \"simple\", \"anon_list_size\": 50, \"own_list_size\": 50, \"num_iter\": 3, \
+     \"setup_time_s\": 1.500000000, \"gen_time_s\": 0.000250000, \"ver_time_s\": 0.000000007, \"proof_size_bytes\": 29333}\n\
+     ]\n");
+  assert_eq!(written(OutputFormat::Json, &[]), "[\n]\n");
+}
+
+// The positional invocation from before the subcommands still runs a single configuration
+#[test]
+fn positional_arguments_default_to_run() {
+  let subcommands = ["run", "sweep"];
+  assert_eq!(with_default_subcommand(args("nummatus 100 25 -n 100"), &subcommands), args("nummatus run 100 25 -n 100"));
+  assert_eq!(with_default_subcommand(args("simple -n 100 25"), &subcommands), args("simple run -n 100 25"));
+  for line in &["nummatus run 100 25", "nummatus sweep -a 100 -o 50", "nummatus --help", "nummatus -V", "nummatus help", "nummatus"] {
+    assert_eq!(with_default_subcommand(args(line), &subcommands), args(line));
+  }
+}
@@ -0,0 +1,103 @@
+use rand::thread_rng;
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::key::{SecretKey, PublicKey, ZERO_KEY};
+
+use quisquis::ledger::{Ledger, create_account};
+use quisquis::misc::{QPublicKey, GENERATOR_G, GENERATOR_H};
+use quisquis::update_nizk::{AccountUpdate, RerandomisePoK, BalanceUpdatePoK};
+
+fn fresh_update(delta: i64) -> (AccountUpdate, PublicKey) {
+  let mut rng = thread_rng();
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let g_basepoint = PublicKey::from_slice(&secp_inst, &GENERATOR_G).unwrap();
+  let key = SecretKey::new(&secp_inst, &mut rng);
+  let (pubkey, commitment) = create_account(&secp_inst, &mut rng, &key, 500);
+  (AccountUpdate::create(pubkey, commitment, g_basepoint, delta), g_basepoint)
+}
+
+fn random_scalar() -> SecretKey {
+  SecretKey::new(&Secp256k1::with_caps(secp::ContextFlag::Commit), &mut thread_rng())
+}
+
+#[test]
+fn honest_updates_verify() {
+  for &delta in &[0, 1, -1, 250, -500] {
+    let (update, g_basepoint) = fresh_update(delta);
+    assert!(update.verify(g_basepoint), "update by {} rejected", delta);
+  }
+
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let (update, _) = fresh_update(5);
+  assert!(!update.verify(PublicKey::from_slice(&secp_inst, &GENERATOR_H).unwrap()));
+}
+
+#[test]
+fn ledger_transfers_verify() {
+  let mut rng = thread_rng();
+  let mut ledger = Ledger::simulate(&mut rng, 2, 1, 2);
+  ledger.advance();
+  let amount = ledger.balance(0) / 2 + 1;
+  let ((sent_id, sent), (received_id, received)) = ledger.transfer(0, 1, amount);
+
+  for (id, update) in &[(sent_id, &sent), (received_id, &received)] {
+    assert!(update.verify(ledger.g_basepoint()));
+    assert!(ledger.account(*id).commitment.y == update.new_commitment.y);
+  }
+  assert!(!ledger.is_live(0, ledger.height()) && !ledger.is_live(1, ledger.height()));
+}
+
+#[test]
+fn tampered_rerandomise_proofs_are_rejected() {
+  let tampered: [fn(&mut RerandomisePoK); 4] = [
+    |pok| pok.e = random_scalar(),
+    |pok| pok.s = random_scalar(),
+    |pok| pok.e = ZERO_KEY,
+    |pok| pok.s = ZERO_KEY,
+  ];
+  for tamper in &tampered {
+    let (mut update, g_basepoint) = fresh_update(10);
+    tamper(&mut update.rerandomise_pok);
+    assert!(!update.verify(g_basepoint));
+  }
+}
+
+#[test]
+fn tampered_balance_proofs_are_rejected() {
+  let tampered: [fn(&mut BalanceUpdatePoK); 6] = [
+    |pok| pok.e = random_scalar(),
+    |pok| pok.s_t = random_scalar(),
+    |pok| pok.s_v = random_scalar(),
+    |pok| pok.e = ZERO_KEY,
+    |pok| pok.s_t = ZERO_KEY,
+    |pok| pok.s_v = ZERO_KEY,
+  ];
+  for tamper in &tampered {
+    let (mut update, g_basepoint) = fresh_update(-10);
+    tamper(&mut update.balance_pok);
+    assert!(!update.verify(g_basepoint));
+  }
+}
+
+// Statements that make a ratio or product the identity are rejected rather than panicking
+#[test]
+fn degenerate_updates_are_rejected() {
+  let (mut update, g_basepoint) = fresh_update(10);
+  update.new_commitment = update.commitment;
+  assert!(!update.verify(g_basepoint));
+
+  let (mut update, g_basepoint) = fresh_update(10);
+  update.new_commitment.y = update.commitment.y;
+  assert!(!update.verify(g_basepoint));
+
+  let (mut update, g_basepoint) = fresh_update(10);
+  update.new_pubkey = update.pubkey;
+  assert!(!update.verify(g_basepoint));
+
+  let (mut update, g_basepoint) = fresh_update(10);
+  update.new_pubkey = QPublicKey::new();
+  assert!(!update.verify(g_basepoint));
+
+  let (update, _) = fresh_update(10);
+  assert!(!update.verify(PublicKey::new()));
+}
@@ -0,0 +1,346 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use sha2::{Digest, Sha256};
+use secp256k1zkp as secp;
+use secp::Secp256k1;
+use secp::constants::CURVE_ORDER;
+use secp::key::{SecretKey, PublicKey, ZERO_KEY};
+
+use quisquis::misc::{QPublicKey, Nonce, GENERATOR_G, GENERATOR_H};
+use quisquis::misc::{amount_to_key, hash_simple_tx, hash_special_tx, hash_to_scalar, pedersen_commitment, reduce_wide, serialize_point, single_base_product};
+use quisquis::simple_nizk::SimplePoK;
+use quisquis::nummatus_nizk::NummatusPoK;
+
+// Known-answer vectors in tests/vectors/kat.txt: sections of "name = hex"
+// lines, inputs first and expected outputs last, separated by blank lines.
+// Points are 33-byte compressed encodings and scalars 32 bytes big-endian.
+// Every input is derived from a label, so the file can be regenerated with
+//   QUISQUIS_UPDATE_VECTORS=1 cargo test --test vectors
+
+const HEADER: &str = "\
+# Known-answer vectors for the Quisquis proofs of assets.
+# Points are 33-byte compressed encodings and scalars 32 bytes big-endian, in hex.
+# nonce is the auditor nonce absorbed into the challenge, or none.
+# wide is a 64-byte value reduced mod n, with e = none when the result is zero.
+# domain and data are the raw bytes given to hash_to_scalar.
+# x1, y1, x2, y2 and r1 are the prover's randomness.
+";
+
+const AMOUNTS: [u64; 7] = [1, 2, 255, 256, 1000, 1 << 32, u64::MAX];
+
+type Fields = Vec<(String, String)>;
+
+struct Section {
+  name: String,
+  fields: Fields,
+}
+
+fn vectors_path() -> PathBuf {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("vectors").join("kat.txt")
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(s: &str) -> Vec<u8> {
+  (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+}
+
+fn point_hex(secp_inst: &Secp256k1, point: &PublicKey) -> String {
+  to_hex(&serialize_point(secp_inst, point, true))
+}
+
+//the scalar SHA-256("quisquis/kat/" || label)
+fn label_scalar(secp_inst: &Secp256k1, label: &str) -> SecretKey {
+  let mut hasher = Sha256::new();
+  hasher.input(b"quisquis/kat/");
+  hasher.input(label.as_bytes());
+  SecretKey::from_slice(secp_inst, &hasher.result()).unwrap()
+}
+
+//g raised to the scalar of label
+fn label_point(secp_inst: &Secp256k1, label: &str) -> PublicKey {
+  let g_basepoint = PublicKey::from_slice(secp_inst, &GENERATOR_G).unwrap();
+  single_base_product(secp_inst, g_basepoint, label_scalar(secp_inst, label))
+}
+
+fn label_nonce(secp_inst: &Secp256k1, label: &str) -> Nonce {
+  Nonce(label_scalar(secp_inst, label).0)
+}
+
+// Looks up inputs of a section by name
+struct Inputs<'a> {
+  secp_inst: &'a Secp256k1,
+  fields: &'a Fields,
+}
+
+impl<'a> Inputs<'a> {
+  fn get(&self, name: &str) -> &'a str {
+    &self.fields.iter().find(|(key, _)| key == name).unwrap_or_else(|| panic!("missing input {}", name)).1
+  }
+
+  fn point(&self, name: &str) -> PublicKey {
+    PublicKey::from_slice(self.secp_inst, &from_hex(self.get(name))).unwrap()
+  }
+
+  fn scalar(&self, name: &str) -> SecretKey {
+    SecretKey::from_slice(self.secp_inst, &from_hex(self.get(name))).unwrap()
+  }
+
+  fn nonce(&self) -> Option<Nonce> {
+    match self.get("nonce") {
+      "none" => None,
+      hex => Some(Nonce::from_str(hex).unwrap()),
+    }
+  }
+
+  fn account(&self) -> (QPublicKey, QPublicKey) {
+    let mut pubkey = QPublicKey::new();
+    pubkey.x = self.point("a");
+    pubkey.y = self.point("b");
+    let mut commitment = QPublicKey::new();
+    commitment.x = self.point("c");
+    commitment.y = self.point("d");
+    (pubkey, commitment)
+  }
+}
+
+// Names of the outputs of each kind of section, in file order
+fn output_names(section: &str) -> &'static [&'static str] {
+  match section {
+    "amount_to_key" => &["key"],
+    "reduce_wide" | "hash_to_scalar" => &["e"],
+    "hash_simple_tx" | "hash_special_tx" => &["e"],
+    "simple_pok" => &["e", "s", "pok"],
+    "nummatus_pok" => &["e1", "e2", "s1", "s2", "pok"],
+    _ => panic!("unknown section {}", section),
+  }
+}
+
+// Computes the outputs of a section from its inputs
+fn compute(secp_inst: &Secp256k1, section: &str, fields: &Fields) -> Fields {
+  let inputs = Inputs { secp_inst, fields };
+  let field = |name: &str, value: String| (name.to_string(), value);
+
+  match section {
+    "amount_to_key" => {
+      let amount = inputs.get("amount").parse::<u64>().unwrap();
+      vec![field("key", to_hex(&amount_to_key(secp_inst, amount).0))]
+    },
+    "reduce_wide" => {
+      let e = reduce_wide(secp_inst, &from_hex(inputs.get("wide")));
+      vec![field("e", e.map_or("none".to_string(), |e| to_hex(&e.0)))]
+    },
+    "hash_to_scalar" => {
+      let e = hash_to_scalar(secp_inst, &from_hex(inputs.get("domain")), &from_hex(inputs.get("data")));
+      vec![field("e", to_hex(&e.0))]
+    },
+    "hash_simple_tx" => {
+      let p: Vec<PublicKey> = (1..=8).map(|k| inputs.point(&format!("p{}", k))).collect();
+      let e = hash_simple_tx(secp_inst, p[0], p[1], p[2], p[3], p[4], p[5], p[6], p[7], inputs.nonce().as_ref());
+      vec![field("e", to_hex(&e.0))]
+    },
+    "hash_special_tx" => {
+      let p: Vec<PublicKey> = (1..=9).map(|k| inputs.point(&format!("p{}", k))).collect();
+      let e = hash_special_tx(secp_inst, p[0], p[1], p[2], p[3], p[4], p[5], p[6], p[7], p[8], inputs.nonce().as_ref());
+      vec![field("e", to_hex(&e.0))]
+    },
+    "simple_pok" => {
+      let (pubkey, commitment) = inputs.account();
+      let (pederson, h_j, nonce) = (inputs.point("p"), inputs.point("h"), inputs.nonce());
+      let pok = SimplePoK::create_pok_with_nonces(secp_inst, pubkey, commitment, pederson,
+                  inputs.scalar("alpha"), h_j, inputs.scalar("r1"), nonce.as_ref());
+      assert!(SimplePoK::verify_pok(pubkey, commitment, pederson, h_j, pok.clone(), nonce.as_ref()));
+
+      let mut bytes = Vec::new();
+      pok.write_to(&mut bytes).unwrap();
+      vec![field("e", to_hex(&bytes[..32])), field("s", to_hex(&bytes[32..])), field("pok", to_hex(&bytes))]
+    },
+    "nummatus_pok" => {
+      let (pubkey, commitment) = inputs.account();
+      let (pedersen_com, h_j, nonce) = (inputs.point("p"), inputs.point("h"), inputs.nonce());
+      let (own_key, decoy_key) = if fields.iter().any(|(key, _)| key == "alpha") {
+        (inputs.scalar("alpha"), ZERO_KEY)
+      } else {
+        (ZERO_KEY, inputs.scalar("beta"))
+      };
+      let prover_nonces = [inputs.scalar("x1"), inputs.scalar("y1"), inputs.scalar("x2"), inputs.scalar("y2")];
+      let pok = NummatusPoK::create_pok_with_nonces(secp_inst, pubkey, commitment, pedersen_com,
+                  &own_key, &decoy_key, h_j, &prover_nonces, nonce.as_ref());
+      assert!(NummatusPoK::verify_pok(pubkey, commitment, pedersen_com, h_j, pok.clone(), nonce.as_ref()));
+
+      let mut bytes = Vec::new();
+      pok.write_to(&mut bytes).unwrap();
+      let mut outputs: Fields = ["e1", "e2", "s1", "s2"].iter().enumerate()
+        .map(|(k, name)| field(name, to_hex(&bytes[32 * k..32 * (k + 1)])))
+        .collect();
+      outputs.push(field("pok", to_hex(&bytes)));
+      outputs
+    },
+    _ => panic!("unknown section {}", section),
+  }
+}
+
+// Inputs of an account (a, b, c, d) with secret key key and balance amount,
+// with its randomness derived from label
+fn account_fields(secp_inst: &Secp256k1, label: &str, key: &SecretKey, amount: u64) -> Fields {
+  let g_basepoint = PublicKey::from_slice(secp_inst, &GENERATOR_G).unwrap();
+  let a = label_point(secp_inst, &format!("{}/a", label));
+  let b = single_base_product(secp_inst, a, key.clone());
+  let r2 = label_scalar(secp_inst, &format!("{}/r2", label));
+  let c = single_base_product(secp_inst, a, r2.clone());
+  let d = pedersen_commitment(secp_inst, g_basepoint, b, amount, r2);
+
+  vec![
+    ("a".to_string(), point_hex(secp_inst, &a)),
+    ("b".to_string(), point_hex(secp_inst, &b)),
+    ("c".to_string(), point_hex(secp_inst, &c)),
+    ("d".to_string(), point_hex(secp_inst, &d)),
+  ]
+}
+
+fn nonce_field(secp_inst: &Secp256k1, label: &str, with_nonce: bool) -> (String, String) {
+  let value = if with_nonce { to_hex(&label_nonce(secp_inst, &format!("{}/nonce", label)).0) } else { "none".to_string() };
+  ("nonce".to_string(), value)
+}
+
+// Inputs of every vector, in file order
+fn generate_inputs(secp_inst: &Secp256k1) -> Vec<Section> {
+  let g_basepoint = PublicKey::from_slice(secp_inst, &GENERATOR_G).unwrap();
+  let h_basepoint = PublicKey::from_slice(secp_inst, &GENERATOR_H).unwrap();
+  let scalar_field = |name: &str, scalar: &SecretKey| (name.to_string(), to_hex(&scalar.0));
+  let mut sections = Vec::new();
+
+  for amount in AMOUNTS.iter() {
+    sections.push(Section { name: "amount_to_key".to_string(), fields: vec![("amount".to_string(), amount.to_string())] });
+  }
+
+  // Halves at and above n, and values reducing to zero, one and n - 1
+  let mut order_minus_one = CURVE_ORDER;
+  order_minus_one[31] -= 1;
+  let mut one = [0u8; 32];
+  one[31] = 1;
+  for &(hi, lo) in &[([0xff; 32], [0xff; 32]), (CURVE_ORDER, CURVE_ORDER), ([0; 32], one), ([0; 32], order_minus_one), (order_minus_one, CURVE_ORDER)] {
+    let fields = vec![("wide".to_string(), to_hex(&[hi, lo].concat()))];
+    sections.push(Section { name: "reduce_wide".to_string(), fields });
+  }
+
+  for (i, &(domain, data_len)) in [("quisquis/kat", 1), ("quisquis/simplus/challenge", 32), ("quisquis/nummatus/challenge", 64)].iter().enumerate() {
+    let label = format!("hash_to_scalar/{}", i);
+    let data = [label_scalar(secp_inst, &format!("{}/0", label)).0, label_scalar(secp_inst, &format!("{}/1", label)).0].concat();
+    let data = &data[..data_len];
+    let fields = vec![("domain".to_string(), to_hex(domain.as_bytes())), ("data".to_string(), to_hex(data))];
+    sections.push(Section { name: "hash_to_scalar".to_string(), fields });
+  }
+
+  for &(name, num_points) in &[("hash_simple_tx", 8), ("hash_special_tx", 9)] {
+    for (i, &with_nonce) in [false, true].iter().enumerate() {
+      let label = format!("{}/{}", name, i);
+      let mut fields: Fields = (1..=num_points)
+        .map(|k| (format!("p{}", k), point_hex(secp_inst, &label_point(secp_inst, &format!("{}/p{}", label, k)))))
+        .collect();
+      fields.push(nonce_field(secp_inst, &label, with_nonce));
+      sections.push(Section { name: name.to_string(), fields });
+    }
+  }
+
+  for (i, &with_nonce) in [false, true].iter().enumerate() {
+    let label = format!("simple_pok/{}", i);
+    let alpha = label_scalar(secp_inst, &format!("{}/alpha", label));
+    let amount = 100 * (i as u64 + 1);
+
+    let mut fields = vec![("h".to_string(), point_hex(secp_inst, &h_basepoint))];
+    fields.extend(account_fields(secp_inst, &label, &alpha, amount));
+    fields.push(("p".to_string(), point_hex(secp_inst, &pedersen_commitment(secp_inst, g_basepoint, h_basepoint, amount, alpha.clone()))));
+    fields.push(scalar_field("alpha", &alpha));
+    fields.push(scalar_field("r1", &label_scalar(secp_inst, &format!("{}/r1", label))));
+    fields.push(nonce_field(secp_inst, &label, with_nonce));
+    sections.push(Section { name: "simple_pok".to_string(), fields });
+  }
+
+  for (i, &(owned, with_nonce)) in [(true, false), (true, true), (false, false), (false, true)].iter().enumerate() {
+    let label = format!("nummatus_pok/{}", i);
+    let key = label_scalar(secp_inst, &format!("{}/key", label));
+    let amount = 100 * (i as u64 + 1);
+
+    let mut fields = vec![("h".to_string(), point_hex(secp_inst, &h_basepoint))];
+    fields.extend(account_fields(secp_inst, &label, &key, amount));
+    if owned {
+      fields.push(("p".to_string(), point_hex(secp_inst, &pedersen_commitment(secp_inst, g_basepoint, h_basepoint, amount, key.clone()))));
+      fields.push(scalar_field("alpha", &key));
+    } else {
+      let beta = label_scalar(secp_inst, &format!("{}/beta", label));
+      fields.push(("p".to_string(), point_hex(secp_inst, &single_base_product(secp_inst, h_basepoint, beta.clone()))));
+      fields.push(scalar_field("beta", &beta));
+    }
+    for name in &["x1", "y1", "x2", "y2"] {
+      fields.push(scalar_field(name, &label_scalar(secp_inst, &format!("{}/{}", label, name))));
+    }
+    fields.push(nonce_field(secp_inst, &label, with_nonce));
+    sections.push(Section { name: "nummatus_pok".to_string(), fields });
+  }
+
+  sections
+}
+
+fn generate(secp_inst: &Secp256k1) -> String {
+  let mut text = HEADER.to_string();
+  for mut section in generate_inputs(secp_inst) {
+    let outputs = compute(secp_inst, &section.name, &section.fields);
+    section.fields.extend(outputs);
+
+    text.push_str(&format!("\n[{}]\n", section.name));
+    for (key, value) in &section.fields {
+      text.push_str(&format!("{} = {}\n", key, value));
+    }
+  }
+  text
+}
+
+fn parse(text: &str) -> Vec<Section> {
+  let mut sections: Vec<Section> = Vec::new();
+  for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+    if line.starts_with('[') && line.ends_with(']') {
+      sections.push(Section { name: line[1..line.len() - 1].to_string(), fields: Vec::new() });
+    } else {
+      let (key, value) = line.split_at(line.find('=').unwrap_or_else(|| panic!("malformed line {:?}", line)));
+      sections.last_mut().expect("field before the first section")
+        .fields.push((key.trim().to_string(), value[1..].trim().to_string()));
+    }
+  }
+  sections
+}
+
+#[test]
+fn vectors_match_implementation() {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let text = fs::read_to_string(vectors_path()).unwrap();
+  let sections = parse(&text);
+  assert!(!sections.is_empty());
+
+  for (i, section) in sections.iter().enumerate() {
+    let outputs = output_names(&section.name);
+    let (expected, inputs): (Fields, Fields) = section.fields.iter().cloned()
+      .partition(|(key, _)| outputs.contains(&key.as_str()));
+    assert!(expected.len() == outputs.len(), "vector {} ({}) lacks some outputs", i, section.name);
+
+    for (key, value) in compute(&secp_inst, &section.name, &inputs) {
+      let want = &expected.iter().find(|(name, _)| *name == key).unwrap().1;
+      assert!(&value == want, "vector {} ({}): {} is {}, expected {}", i, section.name, key, value, want);
+    }
+  }
+}
+
+#[test]
+fn vectors_file_is_current() {
+  let secp_inst = Secp256k1::with_caps(secp::ContextFlag::Commit);
+  let generated = generate(&secp_inst);
+  if env::var_os("QUISQUIS_UPDATE_VECTORS").is_some() {
+    fs::write(vectors_path(), &generated).unwrap();
+  }
+  assert!(fs::read_to_string(vectors_path()).unwrap() == generated, "vectors are stale, regenerate them with QUISQUIS_UPDATE_VECTORS=1");
+}